@@ -0,0 +1,180 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    game_state::InRun,
+    headless::creatures_app,
+    player_plugin::{ChainLength, LocalPlayers},
+    terrain_plugin::spawn_terrain_cubes,
+};
+
+/// Segmenti del corpo provati, 144 è la creatura del gioco.
+const CHAIN_LENGTHS: [usize; 5] = [50, 144, 500, 1_000, 2_000];
+/// Cubi del terreno provati, 20 è il livello del gioco.
+const TERRAIN_TILES: [usize; 4] = [100, 1_000, 10_000, 100_000];
+/// Frame scartati all'inizio: spawn, broad phase e cache ancora da riempire.
+const WARMUP_FRAMES: usize = 30;
+const MEASURED_FRAMES: usize = 120;
+/// Lato dei cubi del terreno più lo spazio tra due cubi.
+const TILE_STEP: f32 = 105.;
+
+/// Cubi del terreno da spawnare in questa misura.
+#[derive(Resource)]
+struct TerrainTiles(usize);
+
+/// Durata di ogni passo di fisica, da prima di `SyncBackend` a dopo `Writeback`.
+#[derive(Resource, Default)]
+struct PhysicsTimes {
+    start: Option<Instant>,
+    samples: Vec<Duration>,
+}
+
+/// Tempi di una combinazione catena/terreno, in millisecondi.
+pub struct BenchResult {
+    pub segments: usize,
+    pub tiles: usize,
+    pub frame_mean: f64,
+    pub frame_p95: f64,
+    pub physics_mean: f64,
+    pub physics_p95: f64,
+}
+
+impl BenchResult {
+    const CSV_HEADER: &'static str =
+        "segments,tiles,frames,frame_ms_mean,frame_ms_p95,physics_ms_mean,physics_ms_p95";
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{:.3},{:.3},{:.3},{:.3}",
+            self.segments,
+            self.tiles,
+            MEASURED_FRAMES,
+            self.frame_mean,
+            self.frame_p95,
+            self.physics_mean,
+            self.physics_p95
+        )
+    }
+}
+
+/// Misura una creatura di `segments` segmenti che si muove (tasto D tenuto) sopra
+/// un terreno di `tiles` cubi. Il frame è quello di un'app senza rendering: bloom e
+/// GPU non sono inclusi.
+pub fn measure(segments: usize, tiles: usize) -> BenchResult {
+    let mut app = creatures_app(LocalPlayers::new(1, &[]));
+    app.insert_resource(ChainLength(segments))
+        .insert_resource(TerrainTiles(tiles))
+        .init_resource::<PhysicsTimes>()
+        .add_systems(OnEnter(InRun), spawn_tiles)
+        .add_systems(
+            PostUpdate,
+            (
+                start_physics_timer.before(PhysicsSet::SyncBackend),
+                stop_physics_timer.after(PhysicsSet::Writeback),
+            ),
+        );
+    app.finish();
+    app.cleanup();
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::KeyD);
+
+    for _ in 0..WARMUP_FRAMES {
+        app.update();
+    }
+    app.world_mut()
+        .resource_mut::<PhysicsTimes>()
+        .samples
+        .clear();
+
+    let mut frames = Vec::with_capacity(MEASURED_FRAMES);
+    for _ in 0..MEASURED_FRAMES {
+        let start = Instant::now();
+        app.update();
+        frames.push(start.elapsed());
+    }
+    let physics = std::mem::take(&mut app.world_mut().resource_mut::<PhysicsTimes>().samples);
+
+    let (frame_mean, frame_p95) = mean_and_p95(frames);
+    let (physics_mean, physics_p95) = mean_and_p95(physics);
+    BenchResult {
+        segments,
+        tiles,
+        frame_mean,
+        frame_p95,
+        physics_mean,
+        physics_p95,
+    }
+}
+
+fn mean_and_p95(mut samples: Vec<Duration>) -> (f64, f64) {
+    if samples.is_empty() {
+        return (0., 0.);
+    }
+    samples.sort();
+    let ms = |duration: Duration| duration.as_secs_f64() * 1_000.;
+    let mean = samples.iter().copied().map(ms).sum::<f64>() / samples.len() as f64;
+    let p95 = ms(samples[(samples.len() * 95 / 100).min(samples.len() - 1)]);
+    (mean, p95)
+}
+
+/// Griglia quadrata di cubi fissi sotto la creatura, come il terreno del gioco.
+fn spawn_tiles(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    tiles: Res<TerrainTiles>,
+) {
+    let columns = (tiles.0 as f32).sqrt().ceil().max(1.) as usize;
+    let first_x = -(columns as f32) * TILE_STEP / 2.;
+    let cubes = (0..tiles.0).map(|i| {
+        let x = first_x + (i % columns) as f32 * TILE_STEP;
+        let y = -1000. - (i / columns) as f32 * TILE_STEP;
+        let color = Color::hsl(360. * (i % columns) as f32 / columns as f32, 0.95, 0.6);
+        (Transform::from_xyz(x, y, 0.), color)
+    });
+    spawn_terrain_cubes(&mut commands, &mut meshes, &mut materials, cubes);
+}
+
+fn start_physics_timer(mut times: ResMut<PhysicsTimes>) {
+    times.start = Some(Instant::now());
+}
+
+fn stop_physics_timer(mut times: ResMut<PhysicsTimes>) {
+    if let Some(start) = times.start.take() {
+        times.samples.push(start.elapsed());
+    }
+}
+
+/// Misura tutte le combinazioni di [`CHAIN_LENGTHS`] e [`TERRAIN_TILES`] e scrive il
+/// CSV in `output`, o sullo stdout. Ha senso solo in release.
+pub fn run(output: Option<PathBuf>) {
+    let mut csv = vec![BenchResult::CSV_HEADER.to_string()];
+    for segments in CHAIN_LENGTHS {
+        for tiles in TERRAIN_TILES {
+            let result = measure(segments, tiles);
+            // l'avanzamento va su stderr, così lo stdout resta un CSV valido
+            eprintln!(
+                "{segments} segments, {tiles} tiles: frame {:.2} ms, physics {:.2} ms",
+                result.frame_mean, result.physics_mean
+            );
+            csv.push(result.csv_row());
+        }
+    }
+    let csv = csv.join("\n") + "\n";
+
+    match output {
+        Some(path) => {
+            if let Err(err) = fs::write(&path, csv) {
+                println!("could not write {}: {err}", path.display());
+            }
+        }
+        None => print!("{csv}"),
+    }
+}
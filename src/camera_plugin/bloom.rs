@@ -0,0 +1,249 @@
+use bevy::{
+    core_pipeline::{
+        bloom::{Bloom, BloomCompositeMode, BloomPrefilter},
+        tonemapping::Tonemapping,
+    },
+    prelude::*,
+};
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::config::save_config;
+
+pub(super) const BLOOM_CONFIG_FILE: &str = "graphics.ron";
+
+/// Bloom e tonemapping di tutte le camere, salvati in [`BLOOM_CONFIG_FILE`].
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BloomSettings {
+    pub intensity: f32,
+    pub low_frequency_boost: f32,
+    pub low_frequency_boost_curvature: f32,
+    pub high_pass_frequency: f32,
+    pub threshold: f32,
+    pub threshold_softness: f32,
+    pub composite_mode: CompositeMode,
+    pub tonemapper: Tonemapper,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompositeMode {
+    EnergyConserving,
+    Additive,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tonemapper {
+    None,
+    Reinhard,
+    ReinhardLuminance,
+    AcesFitted,
+    AgX,
+    SomewhatBoringDisplayTransform,
+    TonyMcMapface,
+    BlenderFilmic,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BloomPreset {
+    Subtle,
+    Neon,
+    Dreamy,
+}
+
+/// Pannello delle impostazioni grafiche, aperto/chiuso con F2.
+#[derive(Resource, Default)]
+pub(super) struct BloomPanel {
+    open: bool,
+    /// Modifiche non ancora salvate su disco.
+    dirty: bool,
+}
+
+impl Default for BloomSettings {
+    /// I valori con cui il gioco è sempre partito: `Bloom::default()` e TonyMcMapface.
+    fn default() -> Self {
+        Self::from_bloom(&Bloom::NATURAL, Tonemapper::TonyMcMapface)
+    }
+}
+
+impl BloomSettings {
+    fn from_bloom(bloom: &Bloom, tonemapper: Tonemapper) -> Self {
+        Self {
+            intensity: bloom.intensity,
+            low_frequency_boost: bloom.low_frequency_boost,
+            low_frequency_boost_curvature: bloom.low_frequency_boost_curvature,
+            high_pass_frequency: bloom.high_pass_frequency,
+            threshold: bloom.prefilter.threshold,
+            threshold_softness: bloom.prefilter.threshold_softness,
+            composite_mode: match bloom.composite_mode {
+                BloomCompositeMode::EnergyConserving => CompositeMode::EnergyConserving,
+                BloomCompositeMode::Additive => CompositeMode::Additive,
+            },
+            tonemapper,
+        }
+    }
+
+    pub fn preset(preset: BloomPreset) -> Self {
+        match preset {
+            BloomPreset::Subtle => Self {
+                intensity: 0.08,
+                low_frequency_boost: 0.4,
+                ..default()
+            },
+            BloomPreset::Neon => Self {
+                intensity: 0.35,
+                low_frequency_boost: 0.8,
+                threshold: 0.6,
+                threshold_softness: 0.2,
+                composite_mode: CompositeMode::Additive,
+                ..default()
+            },
+            BloomPreset::Dreamy => Self {
+                intensity: 0.5,
+                low_frequency_boost: 0.95,
+                high_pass_frequency: 0.5,
+                tonemapper: Tonemapper::BlenderFilmic,
+                ..default()
+            },
+        }
+    }
+
+    pub fn apply(&self, bloom: &mut Bloom, tonemapping: &mut Tonemapping) {
+        bloom.intensity = self.intensity;
+        bloom.low_frequency_boost = self.low_frequency_boost;
+        bloom.low_frequency_boost_curvature = self.low_frequency_boost_curvature;
+        bloom.high_pass_frequency = self.high_pass_frequency;
+        bloom.prefilter = BloomPrefilter {
+            threshold: self.threshold,
+            threshold_softness: self.threshold_softness,
+        };
+        bloom.composite_mode = match self.composite_mode {
+            CompositeMode::EnergyConserving => BloomCompositeMode::EnergyConserving,
+            CompositeMode::Additive => BloomCompositeMode::Additive,
+        };
+        *tonemapping = self.tonemapper.into();
+    }
+}
+
+impl From<Tonemapper> for Tonemapping {
+    fn from(tonemapper: Tonemapper) -> Self {
+        match tonemapper {
+            Tonemapper::None => Tonemapping::None,
+            Tonemapper::Reinhard => Tonemapping::Reinhard,
+            Tonemapper::ReinhardLuminance => Tonemapping::ReinhardLuminance,
+            Tonemapper::AcesFitted => Tonemapping::AcesFitted,
+            Tonemapper::AgX => Tonemapping::AgX,
+            Tonemapper::SomewhatBoringDisplayTransform => {
+                Tonemapping::SomewhatBoringDisplayTransform
+            }
+            Tonemapper::TonyMcMapface => Tonemapping::TonyMcMapface,
+            Tonemapper::BlenderFilmic => Tonemapping::BlenderFilmic,
+        }
+    }
+}
+
+/// Copia le impostazioni sulle camere quando cambiano, e sulle camere appena create.
+pub(super) fn apply_bloom_settings(
+    settings: Res<BloomSettings>,
+    mut cameras: Query<(&mut Bloom, &mut Tonemapping)>,
+) {
+    for (mut bloom, mut tonemapping) in &mut cameras {
+        if settings.is_changed() || bloom.is_added() {
+            settings.apply(&mut bloom, &mut tonemapping);
+        }
+    }
+}
+
+pub(super) fn bloom_panel(
+    mut contexts: EguiContexts,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut panel: ResMut<BloomPanel>,
+    mut settings: ResMut<BloomSettings>,
+) {
+    if kb_input.just_pressed(KeyCode::F2) {
+        panel.open = !panel.open;
+        // chiudendo il pannello le modifiche vengono salvate
+        if !panel.open && panel.dirty {
+            save_config(BLOOM_CONFIG_FILE, &*settings);
+            panel.dirty = false;
+        }
+    }
+    if !panel.open {
+        return;
+    }
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    // si lavora su una copia, così la risorsa risulta cambiata solo se lo è davvero
+    let mut edited = settings.clone();
+    egui::Window::new("Bloom").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            for (name, preset) in [
+                ("Subtle", BloomPreset::Subtle),
+                ("Neon", BloomPreset::Neon),
+                ("Dreamy", BloomPreset::Dreamy),
+            ] {
+                if ui.button(name).clicked() {
+                    edited = BloomSettings::preset(preset);
+                }
+            }
+            if ui.button("Default").clicked() {
+                edited = BloomSettings::default();
+            }
+        });
+        ui.add(egui::Slider::new(&mut edited.intensity, 0.0..=1.0).text("intensity"));
+        ui.add(
+            egui::Slider::new(&mut edited.low_frequency_boost, 0.0..=1.0)
+                .text("low frequency boost"),
+        );
+        ui.add(
+            egui::Slider::new(&mut edited.low_frequency_boost_curvature, 0.0..=1.0)
+                .text("boost curvature"),
+        );
+        ui.add(
+            egui::Slider::new(&mut edited.high_pass_frequency, 0.0..=1.0)
+                .text("high pass frequency"),
+        );
+        ui.add(egui::Slider::new(&mut edited.threshold, 0.0..=4.0).text("threshold"));
+        ui.add(
+            egui::Slider::new(&mut edited.threshold_softness, 0.0..=1.0).text("threshold softness"),
+        );
+        egui::ComboBox::from_label("composite mode")
+            .selected_text(format!("{:?}", edited.composite_mode))
+            .show_ui(ui, |ui| {
+                for mode in [CompositeMode::EnergyConserving, CompositeMode::Additive] {
+                    ui.selectable_value(&mut edited.composite_mode, mode, format!("{mode:?}"));
+                }
+            });
+        egui::ComboBox::from_label("tonemapper")
+            .selected_text(format!("{:?}", edited.tonemapper))
+            .show_ui(ui, |ui| {
+                for tonemapper in [
+                    Tonemapper::None,
+                    Tonemapper::Reinhard,
+                    Tonemapper::ReinhardLuminance,
+                    Tonemapper::AcesFitted,
+                    Tonemapper::AgX,
+                    Tonemapper::SomewhatBoringDisplayTransform,
+                    Tonemapper::TonyMcMapface,
+                    Tonemapper::BlenderFilmic,
+                ] {
+                    ui.selectable_value(
+                        &mut edited.tonemapper,
+                        tonemapper,
+                        format!("{tonemapper:?}"),
+                    );
+                }
+            });
+        if ui.button("Save").clicked() {
+            save_config(BLOOM_CONFIG_FILE, &edited);
+            panel.dirty = false;
+        }
+    });
+
+    if edited != *settings {
+        *settings = edited;
+        panel.dirty = true;
+    }
+}
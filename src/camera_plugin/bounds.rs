@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+
+use super::{zoom::CameraZoom, CameraSettings};
+use crate::terrain_plugin::LevelBounds;
+
+/// Tiene il rettangolo visibile di ogni camera dentro [`LevelBounds`]: prima limita
+/// lo zoom perché la vista ci stia, poi sposta la camera. Se il livello è più stretto
+/// della vista su un asse (es. finestra molto larga) la camera si centra su quell'asse.
+pub(super) fn confine_camera(
+    mut cameras: Query<(
+        &Camera,
+        &mut Transform,
+        &mut OrthographicProjection,
+        &mut CameraZoom,
+    )>,
+    level: Option<Res<LevelBounds>>,
+    settings: Res<CameraSettings>,
+) {
+    let Some(level) = level else {
+        return;
+    };
+    if !settings.confine_to_level {
+        return;
+    }
+    let bounds = level.0;
+
+    for (camera, mut transform, mut projection, mut zoom) in &mut cameras {
+        let Some(viewport_size) = camera.logical_viewport_size() else {
+            continue;
+        };
+        let fit_scale = (bounds.size() / viewport_size).min_element();
+        if projection.scale > fit_scale {
+            projection.scale = fit_scale;
+        }
+        zoom.target = zoom.target.min(fit_scale);
+
+        let half_view = viewport_size * projection.scale / 2.;
+        let min = bounds.min + half_view;
+        let max = bounds.max - half_view;
+        let center = transform.translation.truncate();
+        let confined = Vec2::new(
+            confine_axis(center.x, min.x, max.x),
+            confine_axis(center.y, min.y, max.y),
+        );
+        transform.translation = confined.extend(transform.translation.z);
+    }
+}
+
+fn confine_axis(value: f32, min: f32, max: f32) -> f32 {
+    if min > max {
+        (min + max) / 2.
+    } else {
+        value.clamp(min, max)
+    }
+}
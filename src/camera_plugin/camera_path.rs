@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    photo::{detach_camera, reattach_camera, DetachedCamera, PhotoMode},
+    PlayerCamera,
+};
+use crate::config::save_config;
+
+pub(super) const CAMERA_PATH_FILE: &str = "camera_path.ron";
+/// Secondi tra un keyframe e il successivo quando vengono aggiunti dalla modalità foto.
+const KEYFRAME_SPACING: f32 = 2.;
+
+/// Curva con cui la camera arriva su un keyframe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    #[default]
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1. - (1. - t).powi(3),
+            Easing::EaseInOut => t * t * (3. - 2. * t),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraKeyframe {
+    /// Secondi dall'inizio del percorso.
+    pub time: f32,
+    pub position: Vec2,
+    pub scale: f32,
+    /// Rotazione attorno all'asse z, in radianti.
+    pub rotation: f32,
+    /// Curva usata per arrivare su questo keyframe dal precedente.
+    pub easing: Easing,
+}
+
+/// Percorso della camera per i trailer, salvato in [`CAMERA_PATH_FILE`].
+/// In modalità foto: I aggiunge la posa attuale, Backspace toglie l'ultima,
+/// Canc svuota. Fuori dalla modalità foto L avvia/ferma la riproduzione.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraPath {
+    /// Ordinati per `time`.
+    pub keyframes: Vec<CameraKeyframe>,
+    pub looping: bool,
+}
+
+impl CameraPath {
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0., |keyframe| keyframe.time)
+    }
+
+    /// Posa interpolata all'istante `time`: posizione e rotazione lineari, scala in
+    /// scala logaritmica, il tutto con l'easing del keyframe d'arrivo.
+    pub fn sample(&self, time: f32) -> Option<(Vec2, f32, f32)> {
+        let first = self.keyframes.first()?;
+        let next = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time > time);
+        let (from, to) = match next {
+            None => {
+                let last = self.keyframes.last()?;
+                return Some((last.position, last.scale, last.rotation));
+            }
+            Some(0) => return Some((first.position, first.scale, first.rotation)),
+            Some(i) => (&self.keyframes[i - 1], &self.keyframes[i]),
+        };
+        let span = (to.time - from.time).max(1e-4);
+        let t = to.easing.apply((time - from.time) / span);
+        Some((
+            from.position.lerp(to.position, t),
+            (from.scale.ln() + (to.scale.ln() - from.scale.ln()) * t).exp(),
+            from.rotation + (to.rotation - from.rotation) * t,
+        ))
+    }
+}
+
+/// Riproduzione del [`CameraPath`] in corso, in secondi reali dall'inizio.
+#[derive(Resource, Default)]
+pub struct CameraPathPlayer {
+    elapsed: Option<f32>,
+}
+
+impl CameraPathPlayer {
+    pub fn is_playing(&self) -> bool {
+        self.elapsed.is_some()
+    }
+
+    pub fn stop(&mut self) {
+        self.elapsed = None;
+    }
+}
+
+pub(super) fn edit_camera_path(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    photo_mode: Res<PhotoMode>,
+    camera: Single<(&Transform, &OrthographicProjection), With<DetachedCamera>>,
+    mut path: ResMut<CameraPath>,
+) {
+    if !photo_mode.active {
+        return;
+    }
+    let (transform, projection) = *camera;
+    if kb_input.just_pressed(KeyCode::KeyI) {
+        let time = path
+            .keyframes
+            .last()
+            .map_or(0., |keyframe| keyframe.time + KEYFRAME_SPACING);
+        path.keyframes.push(CameraKeyframe {
+            time,
+            position: transform.translation.truncate(),
+            scale: projection.scale,
+            rotation: transform.rotation.to_euler(EulerRot::XYZ).2,
+            easing: Easing::default(),
+        });
+    } else if kb_input.just_pressed(KeyCode::Backspace) {
+        path.keyframes.pop();
+    } else if kb_input.just_pressed(KeyCode::Delete) {
+        path.keyframes.clear();
+    } else {
+        return;
+    }
+    save_config(CAMERA_PATH_FILE, &*path);
+}
+
+pub(super) fn play_camera_path(
+    mut commands: Commands,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    photo_mode: Res<PhotoMode>,
+    path: Res<CameraPath>,
+    mut player: ResMut<CameraPathPlayer>,
+    mut cameras: Query<(Entity, &mut Camera, &PlayerCamera)>,
+    mut detached: Query<
+        (Entity, &mut Transform, &mut OrthographicProjection),
+        With<DetachedCamera>,
+    >,
+    time: Res<Time<Real>>,
+) {
+    if photo_mode.active {
+        return;
+    }
+    let toggle = kb_input.just_pressed(KeyCode::KeyL);
+    let Some(elapsed) = player.elapsed else {
+        if toggle && path.keyframes.len() > 1 {
+            player.elapsed = Some(0.);
+            detach_camera(&mut commands, &mut cameras);
+        }
+        return;
+    };
+
+    let mut elapsed = elapsed + time.delta_secs();
+    if path.looping && path.duration() > 0. {
+        elapsed %= path.duration();
+    }
+    let finished = toggle || elapsed > path.duration();
+    for (camera, mut transform, mut projection) in &mut detached {
+        if finished {
+            reattach_camera(&mut commands, camera, &mut transform);
+            continue;
+        }
+        if let Some((position, scale, rotation)) = path.sample(elapsed) {
+            transform.translation = position.extend(transform.translation.z);
+            transform.rotation = Quat::from_rotation_z(rotation);
+            projection.scale = scale;
+        }
+    }
+    player.elapsed = (!finished).then_some(elapsed);
+}
@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::CameraSettings;
+
+/// Come la camera insegue il suo obiettivo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FollowMode {
+    /// Lerp verso la testa con `CAM_LERP_FACTOR`, sempre un po' in ritardo.
+    #[default]
+    Lerp,
+    /// Anticipa la direzione di marcia, ignora i piccoli movimenti dentro la dead zone
+    /// e arriva sull'obiettivo con una molla a smorzamento critico.
+    Dynamic,
+}
+
+/// Stato della molla della camera.
+#[derive(Component, Default)]
+pub(super) struct CameraMotion {
+    pub velocity: Vec2,
+}
+
+pub(super) fn toggle_follow_mode(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<CameraSettings>,
+) {
+    if kb_input.just_pressed(KeyCode::KeyC) {
+        settings.follow_mode = match settings.follow_mode {
+            FollowMode::Lerp => FollowMode::Dynamic,
+            FollowMode::Dynamic => FollowMode::Lerp,
+        };
+    }
+}
+
+/// Punto da raggiungere in modalità [`FollowMode::Dynamic`]: l'obiettivo spostato
+/// nella direzione della velocità, ridotto della parte che cade nella dead zone.
+pub(super) fn dynamic_goal(
+    position: Vec2,
+    goal: Vec2,
+    velocity: Vec2,
+    scale: f32,
+    settings: &CameraSettings,
+) -> Vec2 {
+    let look_ahead =
+        (velocity * settings.look_ahead_time).clamp_length_max(settings.max_look_ahead);
+    let offset = goal + look_ahead - position;
+    // la dead zone è in pixel di schermo, quindi segue lo zoom
+    let dead_zone = settings.dead_zone * scale;
+    position + offset - offset.clamp(-dead_zone, dead_zone)
+}
+
+/// Molla a smorzamento critico (approssimazione di `SmoothDamp`): stabile e
+/// indipendente dal frame rate, `smooth_time` è il tempo per coprire circa metà strada.
+pub(super) fn smooth_damp(
+    position: Vec2,
+    goal: Vec2,
+    velocity: &mut Vec2,
+    smooth_time: f32,
+    dt: f32,
+) -> Vec2 {
+    let omega = 2. / smooth_time.max(1e-4);
+    let x = omega * dt;
+    let decay = 1. / (1. + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = position - goal;
+    let temp = (*velocity + omega * change) * dt;
+    *velocity = (*velocity - omega * temp) * decay;
+    goal + (change + temp) * decay
+}
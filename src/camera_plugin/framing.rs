@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+
+use super::{zoom::CameraZoom, CameraSettings, CameraTarget, SplitScreen};
+use crate::robot_factory::robot_parts::{PartOf, RobotBody, RobotHead};
+
+/// Entità da tenere in quadro insieme alla creatura quando il framing è attivo.
+#[derive(Component, Default)]
+pub struct FramingTarget;
+
+/// Centro del rettangolo che contiene la creatura inseguita, aggiornato ogni frame.
+#[derive(Component, Default)]
+pub(super) struct CameraFraming {
+    pub center: Vec2,
+}
+
+pub(super) fn toggle_framing(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<CameraSettings>,
+) {
+    if kb_input.just_pressed(KeyCode::KeyF) {
+        settings.framing = !settings.framing;
+    }
+}
+
+/// Calcola il rettangolo che contiene tutti i pezzi della creatura seguita (di tutte
+/// le creature a schermo unito) più i [`FramingTarget`], e chiede allo zoom la scala
+/// che lo fa stare nel viewport.
+pub(super) fn frame_creatures(
+    mut cameras: Query<(&Camera, &CameraTarget, &mut CameraFraming, &mut CameraZoom)>,
+    parts: Query<(&PartOf, &GlobalTransform), Or<(With<RobotBody>, With<RobotHead>)>>,
+    targets: Query<&GlobalTransform, With<FramingTarget>>,
+    split_screen: Res<SplitScreen>,
+    settings: Res<CameraSettings>,
+) {
+    for (camera, target, mut framing, mut zoom) in &mut cameras {
+        if !settings.framing {
+            zoom.framed = None;
+            continue;
+        }
+        let whole_group = split_screen.group_center.is_some();
+        let points = parts
+            .iter()
+            .filter(|(part_of, _)| whole_group || part_of.0 == target.0)
+            .map(|(_, transform)| transform)
+            .chain(&targets)
+            .map(|transform| transform.translation().truncate());
+        let Some(bounds) = points.fold(None, |bounds: Option<Rect>, point| {
+            Some(
+                bounds.map_or(Rect::from_center_size(point, Vec2::ZERO), |bounds| {
+                    bounds.union_point(point)
+                }),
+            )
+        }) else {
+            zoom.framed = None;
+            continue;
+        };
+        let Some(viewport_size) = camera.logical_viewport_size() else {
+            continue;
+        };
+
+        let bounds = bounds.inflate(settings.framing_padding);
+        let fit_scale = (bounds.size() / viewport_size).max_element();
+        framing.center = bounds.center();
+        // si allarga solo se serve: lo zoom scelto dal giocatore resta il minimo
+        let needed = zoom.target.max(fit_scale);
+        zoom.framed = Some(zoom.target + (needed - zoom.target) * settings.framing_weight);
+    }
+}
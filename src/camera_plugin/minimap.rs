@@ -0,0 +1,209 @@
+use bevy::{
+    core_pipeline::tonemapping::Tonemapping,
+    prelude::*,
+    render::{camera::Viewport, view::RenderLayers},
+    window::PrimaryWindow,
+};
+
+use super::{CameraTarget, PlayerCamera};
+use crate::player_plugin::{Player, PlayerPalette};
+use crate::robot_factory::robot_parts::{Head, PartOf, RobotBody, RobotHead};
+
+/// Layer visto solo dalla minimappa: contorni delle creature, icone e riquadri delle camere.
+pub const MINIMAP_LAYER: usize = 1;
+
+/// Disegna sulla minimappa; le linee hanno spessore in pixel, quindi restano leggibili
+/// a qualsiasi zoom.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct MinimapGizmos;
+
+/// Entità da segnare sulla minimappa con un pallino (es. pickup).
+#[derive(Component, Clone, Copy)]
+pub struct MinimapIcon {
+    pub color: Color,
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct MinimapSettings {
+    /// Mostra la minimappa (tasto M).
+    pub enabled: bool,
+    /// Lato della minimappa rispetto al lato corto della finestra.
+    pub size: f32,
+    /// Distanza dal bordo della finestra, in pixel logici.
+    pub margin: f32,
+    /// Scala ortografica della minimappa, indipendente da quella dei giocatori.
+    pub scale: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    /// Velocità dello zoom con `-` e `=`, in e-fold al secondo.
+    pub zoom_speed: f32,
+    /// Raggio dei pallini delle creature, in pixel di minimappa.
+    pub marker_radius: f32,
+}
+
+impl Default for MinimapSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            size: 0.25,
+            margin: 16.,
+            scale: 40.,
+            min_scale: 10.,
+            max_scale: 200.,
+            zoom_speed: 1.5,
+            marker_radius: 3.,
+        }
+    }
+}
+
+#[derive(Component)]
+pub(super) struct MinimapCamera;
+
+pub(super) fn setup_minimap(mut commands: Commands, settings: Res<MinimapSettings>) {
+    commands.spawn((
+        Camera2d,
+        MinimapCamera,
+        OrthographicProjection {
+            scale: settings.scale,
+            ..OrthographicProjection::default_2d()
+        },
+        Camera {
+            // stesso formato delle camere dei giocatori, disegnata sopra a tutte
+            hdr: true,
+            order: 100,
+            // si accende in `update_minimap`, una volta calcolato il riquadro
+            is_active: false,
+            clear_color: ClearColorConfig::Custom(Color::srgb(0.02, 0.02, 0.05)),
+            ..default()
+        },
+        Tonemapping::TonyMcMapface,
+        // il mondo (layer 0) più gli elementi riservati alla minimappa
+        RenderLayers::from_layers(&[0, MINIMAP_LAYER]),
+    ));
+}
+
+pub(super) fn hide_minimap(mut camera: Single<&mut Camera, With<MinimapCamera>>) {
+    camera.is_active = false;
+}
+
+pub(super) fn configure_minimap_gizmos(mut config_store: ResMut<GizmoConfigStore>) {
+    let (config, _) = config_store.config_mut::<MinimapGizmos>();
+    config.render_layers = RenderLayers::layer(MINIMAP_LAYER);
+}
+
+pub(super) fn minimap_input(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<MinimapSettings>,
+    time: Res<Time>,
+) {
+    if kb_input.just_pressed(KeyCode::KeyM) {
+        settings.enabled = !settings.enabled;
+    }
+    let mut zoom = 0.;
+    if kb_input.pressed(KeyCode::Minus) {
+        zoom += 1.;
+    }
+    if kb_input.pressed(KeyCode::Equal) {
+        zoom -= 1.;
+    }
+    if zoom != 0. {
+        settings.scale = (settings.scale * (zoom * settings.zoom_speed * time.delta_secs()).exp())
+            .clamp(settings.min_scale, settings.max_scale);
+    }
+}
+
+/// Riquadro quadrato in alto a destra, centrato sul gruppo dei giocatori.
+pub(super) fn update_minimap(
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut minimap: Single<
+        (&mut Camera, &mut Transform, &mut OrthographicProjection),
+        With<MinimapCamera>,
+    >,
+    players: Query<&Head, With<Player>>,
+    heads: Query<&GlobalTransform>,
+    settings: Res<MinimapSettings>,
+) {
+    let (camera, transform, projection) = &mut *minimap;
+    let window_size = window.physical_size();
+    camera.is_active = settings.enabled && window_size.min_element() > 0;
+    if !camera.is_active {
+        return;
+    }
+    let side = (window_size.min_element() as f32 * settings.size) as u32;
+    let margin = (settings.margin * window.scale_factor()) as u32;
+    let side = side
+        .min(window_size.min_element().saturating_sub(2 * margin))
+        .max(1);
+    camera.viewport = Some(Viewport {
+        physical_position: UVec2::new(window_size.x.saturating_sub(side + margin), margin),
+        physical_size: UVec2::splat(side),
+        ..default()
+    });
+    projection.scale = settings.scale;
+
+    let positions: Vec<Vec2> = players
+        .iter()
+        .filter_map(|head| heads.get(head.0).ok())
+        .map(|head| head.translation().truncate())
+        .collect();
+    if !positions.is_empty() {
+        let center = positions.iter().sum::<Vec2>() / positions.len() as f32;
+        transform.translation = center.extend(transform.translation.z);
+    }
+}
+
+/// Contorno semplificato delle creature (un pallino per pezzo, la testa più grande),
+/// le icone e il rettangolo inquadrato da ogni camera attiva.
+pub(super) fn draw_minimap(
+    mut gizmos: Gizmos<MinimapGizmos>,
+    parts: Query<
+        (&PartOf, &GlobalTransform, Has<RobotHead>),
+        Or<(With<RobotBody>, With<RobotHead>)>,
+    >,
+    palettes: Query<&PlayerPalette>,
+    icons: Query<(&MinimapIcon, &GlobalTransform)>,
+    cameras: Query<
+        (
+            &Camera,
+            &Transform,
+            &OrthographicProjection,
+            Option<&CameraTarget>,
+        ),
+        With<PlayerCamera>,
+    >,
+    settings: Res<MinimapSettings>,
+) {
+    if !settings.enabled {
+        return;
+    }
+    let radius = settings.marker_radius * settings.scale;
+    let creature_color = |creature: Entity| {
+        palettes
+            .get(creature)
+            .map_or(Color::WHITE, |palette| palette.head)
+    };
+
+    for (part_of, transform, is_head) in &parts {
+        let scale = if is_head { 2. } else { 1. };
+        gizmos.circle_2d(
+            transform.translation().truncate(),
+            radius * scale,
+            creature_color(part_of.0),
+        );
+    }
+    for (icon, transform) in &icons {
+        gizmos.circle_2d(transform.translation().truncate(), radius * 1.5, icon.color);
+    }
+    for (camera, transform, projection, target) in &cameras {
+        let Some(viewport_size) = camera.logical_viewport_size().filter(|_| camera.is_active)
+        else {
+            continue;
+        };
+        let color = target.map_or(Color::WHITE, |target| creature_color(target.0));
+        gizmos.rect_2d(
+            transform.translation.truncate(),
+            viewport_size * projection.scale,
+            color,
+        );
+    }
+}
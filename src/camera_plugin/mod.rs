@@ -0,0 +1,332 @@
+#![allow(clippy::type_complexity)]
+
+mod bloom;
+mod bounds;
+mod camera_path;
+mod follow;
+mod framing;
+mod minimap;
+mod photo;
+mod shake;
+mod split_screen;
+mod zoom;
+
+use bevy::core_pipeline::bloom::Bloom;
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::prelude::*;
+use bevy_egui::EguiPlugin;
+use bevy_rapier2d::prelude::Velocity;
+use serde::{Deserialize, Serialize};
+
+use crate::config::load_config;
+use crate::game_state::{GameState, InRun};
+use crate::player_plugin::{Player, PlayerSlot};
+use crate::robot_factory::robot_parts::{Head, Robot, RobotBody, RobotHead};
+pub use photo::{DetachedCamera, PhotoMode};
+use photo::{photo_controls, toggle_photo_mode};
+pub use shake::ShakeSettings;
+use shake::{add_trauma, apply_shake, enable_impact_events, remove_shake, CameraShake, HitStop};
+pub use split_screen::SplitScreen;
+use bloom::{apply_bloom_settings, bloom_panel, BloomPanel, BLOOM_CONFIG_FILE};
+pub use bloom::{BloomPreset, BloomSettings, CompositeMode, Tonemapper};
+use bounds::confine_camera;
+pub use camera_path::{CameraKeyframe, CameraPath, CameraPathPlayer, Easing};
+use camera_path::{edit_camera_path, play_camera_path, CAMERA_PATH_FILE};
+pub use follow::FollowMode;
+use follow::{dynamic_goal, smooth_damp, toggle_follow_mode, CameraMotion};
+pub use framing::FramingTarget;
+use framing::{frame_creatures, toggle_framing, CameraFraming};
+pub use minimap::{MinimapGizmos, MinimapIcon, MinimapSettings, MINIMAP_LAYER};
+use minimap::{
+    configure_minimap_gizmos, draw_minimap, hide_minimap, minimap_input, setup_minimap,
+    update_minimap,
+};
+use split_screen::update_split_screen;
+use zoom::{apply_zoom, zoom_input, CameraZoom};
+
+/// Camera lerp factor.
+const CAM_LERP_FACTOR: f32 = 3.7;
+/// File di configurazione di [`CameraSettings`], letto all'avvio.
+const CAMERA_CONFIG_FILE: &str = "camera.ron";
+
+/// Creatura (entità con [`Head`]) seguita dalla camera.
+#[derive(Component, Clone, Copy)]
+pub struct CameraTarget(pub Entity);
+
+/// Parametri della camera, condivisi da tutte le camere dei giocatori. Letti da
+/// [`CAMERA_CONFIG_FILE`]; i campi mancanti prendono i valori di default.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    /// Scala ortografica iniziale (unità di mondo per pixel).
+    pub initial_scale: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    /// Fattore di scala per ogni scatto della rotella.
+    pub wheel_zoom_step: f32,
+    /// Velocità dello zoom da tastiera, in e-fold al secondo.
+    pub key_zoom_speed: f32,
+    /// Rapidità con cui la scala raggiunge quella obiettivo (1/s).
+    pub zoom_smoothing: f32,
+    /// Inquadra tutta la creatura invece della sola testa (tasto F).
+    pub framing: bool,
+    /// Quanto il framing prevale sull'inseguimento della testa, da 0 a 1.
+    pub framing_weight: f32,
+    /// Margine attorno ai pezzi inquadrati, in unità di mondo.
+    pub framing_padding: f32,
+    /// Modalità di inseguimento (tasto C per cambiarla).
+    pub follow_mode: FollowMode,
+    /// Secondi di anticipo sulla velocità della testa.
+    pub look_ahead_time: f32,
+    /// Anticipo massimo, in unità di mondo.
+    pub max_look_ahead: f32,
+    /// Semi-dimensioni della dead zone, in pixel di schermo.
+    pub dead_zone: Vec2,
+    /// Tempo di assestamento della molla, in secondi.
+    pub smooth_time: f32,
+    /// Non mostra niente fuori da `LevelBounds`, se il livello ne definisce. Spento
+    /// di default: i bordi coprono solo il terreno e la creatura ne esce.
+    pub confine_to_level: bool,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            initial_scale: 3. * 2.,
+            min_scale: 0.5,
+            max_scale: 40.,
+            wheel_zoom_step: 1.15,
+            key_zoom_speed: 1.,
+            zoom_smoothing: 10.,
+            framing: false,
+            framing_weight: 0.8,
+            framing_padding: 300.,
+            follow_mode: FollowMode::default(),
+            look_ahead_time: 0.35,
+            max_look_ahead: 1_500.,
+            dead_zone: Vec2::new(60., 40.),
+            smooth_time: 0.25,
+            confine_to_level: false,
+        }
+    }
+}
+
+/// Camera del giocatore locale nello slot indicato (vedi [`PlayerSlot`]).
+#[derive(Component, Clone, Copy)]
+pub struct PlayerCamera(pub usize);
+
+pub struct CameraPlugin;
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SplitScreen>()
+            .insert_resource(load_config::<CameraSettings>(CAMERA_CONFIG_FILE))
+            .init_resource::<ShakeSettings>()
+            .init_resource::<HitStop>()
+            .insert_resource(load_config::<BloomSettings>(BLOOM_CONFIG_FILE))
+            .init_resource::<BloomPanel>()
+            .init_resource::<MinimapSettings>()
+            .init_gizmo_group::<MinimapGizmos>()
+            .init_resource::<PhotoMode>()
+            .insert_resource(load_config::<CameraPath>(CAMERA_PATH_FILE))
+            .init_resource::<CameraPathPlayer>()
+            .add_systems(Startup, (setup_camera, setup_minimap, configure_minimap_gizmos))
+            .add_systems(OnExit(InRun), (reset_cameras, hide_minimap))
+            .add_systems(Update, (bloom_panel, apply_bloom_settings).chain())
+            .add_systems(
+                Update,
+                (
+                    (
+                        toggle_photo_mode,
+                        photo_controls,
+                        edit_camera_path,
+                        play_camera_path,
+                    )
+                        .chain()
+                        .run_if(in_state(GameState::Playing)),
+                    remove_shake,
+                    attach_player_cameras,
+                    toggle_framing,
+                    toggle_follow_mode,
+                    // la camera staccata non insegue nessuno e copre tutto lo schermo
+                    (
+                        update_split_screen,
+                        frame_creatures,
+                        update_camera,
+                        zoom_input,
+                        apply_zoom,
+                        confine_camera,
+                    )
+                        .chain()
+                        .run_if(not(any_with_component::<DetachedCamera>)),
+                    enable_impact_events,
+                    add_trauma,
+                    apply_shake,
+                )
+                    .chain()
+                    .run_if(in_state(InRun)),
+            )
+            .add_systems(
+                Update,
+                (minimap_input, update_minimap, draw_minimap)
+                    .chain()
+                    .after(apply_shake)
+                    .run_if(in_state(InRun)),
+            );
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+    }
+}
+
+fn player_camera(slot: usize, settings: &CameraSettings) -> impl Bundle {
+    let scale = settings
+        .initial_scale
+        .clamp(settings.min_scale, settings.max_scale);
+    (
+        Camera2d,
+        PlayerCamera(slot),
+        OrthographicProjection {
+            scale,
+            ..OrthographicProjection::default_2d()
+        },
+        CameraZoom::new(scale),
+        CameraFraming::default(),
+        CameraMotion::default(),
+        CameraShake::default(),
+        Camera {
+            hdr: true, // 1. HDR is required for bloom
+            order: slot as isize,
+            ..default()
+        },
+        Tonemapping::TonyMcMapface, // 2. Using a tonemapper that desaturates to white is recommended
+        Bloom::default(),           // 3. Enable bloom for the camera
+    )
+}
+
+fn setup_camera(mut commands: Commands, settings: Res<CameraSettings>) {
+    // l'interfaccia va sulla camera principale, non su quella col numero d'ordine
+    // più alto (la minimappa)
+    commands.spawn((player_camera(0, &settings), IsDefaultUiCamera));
+}
+
+/// Tornando al menu resta solo la camera principale, ferma al centro e senza
+/// obiettivo: la partita successiva la riassegna in `attach_player_cameras`.
+fn reset_cameras(
+    mut commands: Commands,
+    mut cameras: Query<(
+        Entity,
+        &PlayerCamera,
+        &mut Camera,
+        &mut Transform,
+        &mut OrthographicProjection,
+        &mut CameraZoom,
+        &mut CameraShake,
+    )>,
+    mut split_screen: ResMut<SplitScreen>,
+    mut path_player: ResMut<CameraPathPlayer>,
+    settings: Res<CameraSettings>,
+) {
+    *split_screen = SplitScreen::default();
+    path_player.stop();
+    for (entity, slot, mut camera, mut transform, mut projection, mut zoom, mut shake) in
+        &mut cameras
+    {
+        if slot.0 != 0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        commands
+            .entity(entity)
+            .remove::<(CameraTarget, DetachedCamera)>();
+        camera.is_active = true;
+        camera.viewport = None;
+        *transform = Transform::default();
+        let scale = settings
+            .initial_scale
+            .clamp(settings.min_scale, settings.max_scale);
+        projection.scale = scale;
+        *zoom = CameraZoom::new(scale);
+        *shake = CameraShake::default();
+    }
+}
+
+/// Ogni player ha la sua camera: quella dello slot 0 esiste già, le altre vengono
+/// create quando compare il player corrispondente.
+fn attach_player_cameras(
+    mut commands: Commands,
+    cameras: Query<(Entity, &PlayerCamera, Option<&CameraTarget>)>,
+    players: Query<(Entity, &PlayerSlot), (With<Player>, With<Head>)>,
+    settings: Res<CameraSettings>,
+) {
+    for (player, slot) in &players {
+        match cameras.iter().find(|(_, camera, _)| camera.0 == slot.0) {
+            Some((_, _, Some(target))) if target.0 == player => {}
+            // senza target, o il player seguito è stato sostituito (es. caricando uno snapshot)
+            Some((camera, _, _)) => {
+                commands.entity(camera).insert(CameraTarget(player));
+            }
+            None => {
+                commands.spawn((player_camera(slot.0, &settings), CameraTarget(player)));
+            }
+        }
+    }
+}
+
+/// Update the camera position by tracking the player.
+fn update_camera(
+    mut camera_query: Query<
+        (
+            &mut Transform,
+            &OrthographicProjection,
+            &CameraTarget,
+            &CameraFraming,
+            &mut CameraMotion,
+        ),
+        With<Camera2d>,
+    >,
+    creatures: Query<&Head>,
+    heads: Query<(&GlobalTransform, &Velocity), (With<RobotHead>, Without<Camera2d>)>,
+    split_screen: Res<SplitScreen>,
+    settings: Res<CameraSettings>,
+    time: Res<Time>,
+) {
+    for (mut transform, projection, target, framing, mut motion) in &mut camera_query {
+        let Some((player, velocity)) = creatures
+            .get(target.0)
+            .ok()
+            .and_then(|head| heads.get(head.0).ok())
+        else {
+            continue;
+        };
+
+        // a schermo unito l'unica camera attiva inquadra il centro del gruppo
+        let mut goal = split_screen
+            .group_center
+            .unwrap_or(player.translation().truncate());
+        if settings.framing {
+            goal = goal.lerp(framing.center, settings.framing_weight);
+        }
+
+        let position = transform.translation.truncate();
+        let Vec2 { x, y } = match settings.follow_mode {
+            FollowMode::Lerp => position.lerp(goal, time.delta_secs() * CAM_LERP_FACTOR),
+            FollowMode::Dynamic => {
+                // il gruppo non ha una sola velocità da anticipare
+                let velocity = if split_screen.group_center.is_some() {
+                    Vec2::ZERO
+                } else {
+                    velocity.linvel
+                };
+                let goal = dynamic_goal(position, goal, velocity, projection.scale, &settings);
+                smooth_damp(
+                    position,
+                    goal,
+                    &mut motion.velocity,
+                    settings.smooth_time,
+                    time.delta_secs(),
+                )
+            }
+        };
+        transform.translation = Vec3::new(x, y, transform.translation.z);
+    }
+}
@@ -0,0 +1,221 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    render::view::screenshot::{save_to_disk, Screenshot},
+};
+use bevy_rapier2d::prelude::*;
+
+use super::{camera_path::CameraPathPlayer, CameraSettings, MinimapSettings, PlayerCamera};
+
+/// Cartella in cui finiscono le foto, relativa alla cartella di lavoro.
+const SCREENSHOT_DIR: &str = "screenshots";
+/// Velocità di spostamento della camera libera, in pixel di schermo al secondo.
+const PAN_SPEED: f32 = 600.;
+/// Velocità di rotazione della camera libera, in radianti al secondo.
+const ROTATE_SPEED: f32 = 1.;
+
+/// Camera staccata dall'inseguimento (modalità foto o percorso in riproduzione):
+/// inseguimento, zoom, framing e shake la ignorano.
+#[derive(Component)]
+pub struct DetachedCamera;
+
+/// Modalità foto (tasto P): fisica ferma, camera libera, HUD nascosto (tasto H)
+/// e foto con Invio.
+#[derive(Resource, Default)]
+pub struct PhotoMode {
+    pub active: bool,
+    hud_hidden: bool,
+    /// Stato della minimappa all'ingresso, da ripristinare all'uscita.
+    minimap_enabled: bool,
+}
+
+/// Nodi UI nascosti dalla modalità foto.
+#[derive(Component)]
+pub(super) struct HiddenByPhotoMode;
+
+/// Camera principale (slot 0) a tutto schermo, le altre spente: lo split screen è
+/// sospeso finché la camera è staccata.
+pub(super) fn detach_camera(
+    commands: &mut Commands,
+    cameras: &mut Query<(Entity, &mut Camera, &PlayerCamera)>,
+) -> Option<Entity> {
+    let mut detached = None;
+    for (entity, mut camera, slot) in cameras {
+        camera.is_active = slot.0 == 0;
+        if slot.0 == 0 {
+            camera.viewport = None;
+            commands.entity(entity).insert(DetachedCamera);
+            detached = Some(entity);
+        }
+    }
+    detached
+}
+
+/// Restituisce la camera all'inseguimento, dritta.
+pub(super) fn reattach_camera(commands: &mut Commands, camera: Entity, transform: &mut Transform) {
+    transform.rotation = Quat::IDENTITY;
+    commands.entity(camera).remove::<DetachedCamera>();
+}
+
+pub(super) fn toggle_photo_mode(
+    mut commands: Commands,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut path_player: ResMut<CameraPathPlayer>,
+    mut cameras: Query<(Entity, &mut Camera, &PlayerCamera)>,
+    mut detached: Query<(Entity, &mut Transform), With<DetachedCamera>>,
+    mut rapier_config: Query<&mut RapierConfiguration>,
+    mut minimap: ResMut<MinimapSettings>,
+    mut ui_roots: Query<
+        (Entity, &mut Visibility, Has<HiddenByPhotoMode>),
+        (With<Node>, Without<Parent>),
+    >,
+) {
+    if !kb_input.just_pressed(KeyCode::KeyP) {
+        return;
+    }
+    photo_mode.active = !photo_mode.active;
+    for mut config in &mut rapier_config {
+        config.physics_pipeline_active = !photo_mode.active;
+    }
+
+    if photo_mode.active {
+        path_player.stop();
+        detach_camera(&mut commands, &mut cameras);
+        photo_mode.minimap_enabled = minimap.enabled;
+        set_hud_hidden(
+            &mut commands,
+            &mut photo_mode,
+            &mut minimap,
+            &mut ui_roots,
+            true,
+        );
+    } else {
+        for (camera, mut transform) in &mut detached {
+            reattach_camera(&mut commands, camera, &mut transform);
+        }
+        set_hud_hidden(
+            &mut commands,
+            &mut photo_mode,
+            &mut minimap,
+            &mut ui_roots,
+            false,
+        );
+    }
+}
+
+fn set_hud_hidden(
+    commands: &mut Commands,
+    photo_mode: &mut PhotoMode,
+    minimap: &mut MinimapSettings,
+    ui_roots: &mut Query<
+        (Entity, &mut Visibility, Has<HiddenByPhotoMode>),
+        (With<Node>, Without<Parent>),
+    >,
+    hidden: bool,
+) {
+    photo_mode.hud_hidden = hidden;
+    minimap.enabled = !hidden && photo_mode.minimap_enabled;
+    // si ripristinano solo i nodi nascosti da qui, non quelli già nascosti prima
+    for (node, mut visibility, hidden_by_photo) in ui_roots {
+        if hidden && *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+            commands.entity(node).insert(HiddenByPhotoMode);
+        } else if !hidden && hidden_by_photo {
+            *visibility = Visibility::Inherited;
+            commands.entity(node).remove::<HiddenByPhotoMode>();
+        }
+    }
+}
+
+/// Frecce o WASD per spostarsi, Q/E per ruotare, rotella o tastierino +/- per lo zoom,
+/// R per raddrizzare, H per mostrare/nascondere l'HUD e Invio per salvare una foto.
+/// Usa il tempo reale: il gioco potrebbe essere fermo.
+pub(super) fn photo_controls(
+    mut commands: Commands,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut wheel: EventReader<MouseWheel>,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut camera: Single<(&mut Transform, &mut OrthographicProjection), With<DetachedCamera>>,
+    mut minimap: ResMut<MinimapSettings>,
+    mut ui_roots: Query<
+        (Entity, &mut Visibility, Has<HiddenByPhotoMode>),
+        (With<Node>, Without<Parent>),
+    >,
+    settings: Res<CameraSettings>,
+    time: Res<Time<Real>>,
+) {
+    if !photo_mode.active {
+        wheel.clear();
+        return;
+    }
+    let dt = time.delta_secs();
+    let (transform, projection) = &mut *camera;
+
+    let axis = |negative: &[KeyCode], positive: &[KeyCode]| {
+        kb_input.any_pressed(positive.iter().copied()) as i32 as f32
+            - kb_input.any_pressed(negative.iter().copied()) as i32 as f32
+    };
+    let direction = Vec2::new(
+        axis(
+            &[KeyCode::KeyA, KeyCode::ArrowLeft],
+            &[KeyCode::KeyD, KeyCode::ArrowRight],
+        ),
+        axis(
+            &[KeyCode::KeyS, KeyCode::ArrowDown],
+            &[KeyCode::KeyW, KeyCode::ArrowUp],
+        ),
+    );
+    // si muove nel sistema della camera, quindi "su" resta su anche se ruotata
+    let pan = transform.rotation * (direction * PAN_SPEED * projection.scale * dt).extend(0.);
+    transform.translation += pan;
+
+    let turn = axis(&[KeyCode::KeyE], &[KeyCode::KeyQ]);
+    transform.rotate_z(turn * ROTATE_SPEED * dt);
+    if kb_input.just_pressed(KeyCode::KeyR) {
+        transform.rotation = Quat::IDENTITY;
+    }
+
+    let notches: f32 = wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 100.,
+        })
+        .sum();
+    let key_zoom = axis(&[KeyCode::NumpadSubtract], &[KeyCode::NumpadAdd]);
+    projection.scale = (projection.scale
+        * settings.wheel_zoom_step.powf(-notches)
+        * (key_zoom * settings.key_zoom_speed * dt).exp())
+    .clamp(settings.min_scale, settings.max_scale);
+
+    if kb_input.just_pressed(KeyCode::KeyH) {
+        let hidden = !photo_mode.hud_hidden;
+        set_hud_hidden(
+            &mut commands,
+            &mut photo_mode,
+            &mut minimap,
+            &mut ui_roots,
+            hidden,
+        );
+    }
+    if kb_input.just_pressed(KeyCode::Enter) {
+        take_photo(&mut commands);
+    }
+}
+
+fn take_photo(commands: &mut Commands) {
+    if let Err(err) = std::fs::create_dir_all(SCREENSHOT_DIR) {
+        println!("could not create {SCREENSHOT_DIR}: {err}");
+        return;
+    }
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    let path = format!("{SCREENSHOT_DIR}/photo_{stamp}.png");
+    commands
+        .spawn(Screenshot::primary_window())
+        .observe(save_to_disk(path));
+}
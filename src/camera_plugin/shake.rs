@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{CameraTarget, DetachedCamera, SplitScreen};
+use crate::robot_factory::robot_parts::PartOf;
+
+/// Parametri di screen shake e hit-stop. `enabled` a `false` li spegne entrambi
+/// (accessibilità).
+#[derive(Resource, Clone, Debug)]
+pub struct ShakeSettings {
+    pub enabled: bool,
+    /// Moltiplicatore globale dell'ampiezza.
+    pub intensity: f32,
+    /// Spostamento massimo, in pixel di schermo.
+    pub max_offset: f32,
+    /// Rotazione massima, in radianti.
+    pub max_angle: f32,
+    /// Trauma perso ogni secondo.
+    pub trauma_decay: f32,
+    /// Forze di contatto sotto questa soglia non generano eventi.
+    pub min_force: f32,
+    /// Forza che porta il trauma al massimo con un solo urto.
+    pub full_trauma_force: f32,
+    pub hit_stop: bool,
+    /// Forza oltre la quale il tempo rallenta per un attimo.
+    pub hit_stop_force: f32,
+    /// Durata del rallentamento, in secondi reali.
+    pub hit_stop_duration: f32,
+    pub hit_stop_time_scale: f32,
+}
+
+impl Default for ShakeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            intensity: 1.,
+            max_offset: 40.,
+            max_angle: 0.04,
+            trauma_decay: 1.5,
+            min_force: 20_000.,
+            full_trauma_force: 400_000.,
+            hit_stop: true,
+            hit_stop_force: 300_000.,
+            hit_stop_duration: 0.06,
+            hit_stop_time_scale: 0.05,
+        }
+    }
+}
+
+/// Trauma accumulato dalla camera (0..1) e spostamento applicato in questo frame.
+#[derive(Component, Default)]
+pub(super) struct CameraShake {
+    trauma: f32,
+    offset: Vec2,
+}
+
+/// Tempo reale rimasto al rallentamento in corso.
+#[derive(Resource, Default)]
+pub(super) struct HitStop {
+    remaining: f32,
+}
+
+/// I pezzi delle creature devono generare `ContactForceEvent` per far tremare la camera.
+/// Il flag si somma a quelli già presenti (es. i `CollisionEvent` del log).
+pub(super) fn enable_impact_events(
+    mut commands: Commands,
+    parts: Query<Entity, Added<PartOf>>,
+    settings: Res<ShakeSettings>,
+) {
+    for part in &parts {
+        commands
+            .entity(part)
+            .insert(ContactForceEventThreshold(settings.min_force))
+            .entry::<ActiveEvents>()
+            .and_modify(|mut events| *events |= ActiveEvents::CONTACT_FORCE_EVENTS)
+            .or_insert(ActiveEvents::CONTACT_FORCE_EVENTS);
+    }
+}
+
+/// Toglie lo spostamento del frame precedente, così inseguimento e zoom lavorano
+/// sempre sulla posizione "vera" della camera.
+pub(super) fn remove_shake(
+    mut cameras: Query<(&mut Transform, &mut CameraShake), Without<DetachedCamera>>,
+) {
+    for (mut transform, mut shake) in &mut cameras {
+        transform.translation -= shake.offset.extend(0.);
+        transform.rotation = Quat::IDENTITY;
+        shake.offset = Vec2::ZERO;
+    }
+}
+
+pub(super) fn add_trauma(
+    mut contact_force_events: EventReader<ContactForceEvent>,
+    parts: Query<&PartOf>,
+    mut cameras: Query<(&CameraTarget, &mut CameraShake)>,
+    split_screen: Res<SplitScreen>,
+    settings: Res<ShakeSettings>,
+    mut hit_stop: ResMut<HitStop>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    for event in contact_force_events.read() {
+        if !settings.enabled {
+            continue;
+        }
+        let creatures = [event.collider1, event.collider2].map(|collider| parts.get(collider).ok());
+        if creatures.iter().all(Option::is_none) {
+            continue;
+        }
+        let trauma = event.total_force_magnitude / settings.full_trauma_force;
+        for (target, mut shake) in &mut cameras {
+            // a schermo unito l'unica camera mostra tutti
+            let involved = split_screen.group_center.is_some()
+                || creatures
+                    .iter()
+                    .flatten()
+                    .any(|part_of| part_of.0 == target.0);
+            if involved {
+                shake.trauma = (shake.trauma + trauma).min(1.);
+            }
+        }
+        if settings.hit_stop && event.total_force_magnitude >= settings.hit_stop_force {
+            hit_stop.remaining = settings.hit_stop_duration;
+            time.set_relative_speed(settings.hit_stop_time_scale);
+        }
+    }
+}
+
+/// Scuote le camere in proporzione al quadrato del trauma e fa scadere l'hit-stop.
+/// Usa il tempo reale, quindi continua anche mentre il gioco è rallentato.
+pub(super) fn apply_shake(
+    mut cameras: Query<
+        (&mut Transform, &OrthographicProjection, &mut CameraShake),
+        Without<DetachedCamera>,
+    >,
+    settings: Res<ShakeSettings>,
+    mut hit_stop: ResMut<HitStop>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    real_time: Res<Time<Real>>,
+) {
+    let dt = real_time.delta_secs();
+    if hit_stop.remaining > 0. {
+        hit_stop.remaining -= dt;
+        if hit_stop.remaining <= 0. {
+            virtual_time.set_relative_speed(1.);
+        }
+    }
+
+    let t = real_time.elapsed_secs();
+    for (mut transform, projection, mut shake) in &mut cameras {
+        shake.trauma = (shake.trauma - settings.trauma_decay * dt).max(0.);
+        if !settings.enabled || shake.trauma == 0. {
+            continue;
+        }
+        let amount = shake.trauma * shake.trauma * settings.intensity;
+        // somme di sinusoidi a frequenze non multiple: abbastanza irregolari, e continue
+        let noise = |seed: f32| (t * 37. + seed).sin() * 0.6 + (t * 61. + seed * 3.).sin() * 0.4;
+        shake.offset =
+            Vec2::new(noise(0.), noise(17.)) * settings.max_offset * amount * projection.scale;
+        transform.translation += shake.offset.extend(0.);
+        transform.rotation = Quat::from_rotation_z(noise(31.) * settings.max_angle * amount);
+    }
+}
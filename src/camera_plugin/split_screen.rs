@@ -0,0 +1,69 @@
+use bevy::{prelude::*, render::camera::Viewport, window::PrimaryWindow};
+
+use super::PlayerCamera;
+use crate::{player_plugin::Player, robot_factory::robot_parts::Head};
+
+/// Sotto questa distanza tra le teste lo schermo si unisce...
+const MERGE_DISTANCE: f32 = 2_500.;
+/// ...e sopra questa si divide di nuovo; la differenza evita che lampeggi.
+const SPLIT_DISTANCE: f32 = 3_500.;
+
+#[derive(Resource, Default)]
+pub struct SplitScreen {
+    pub merged: bool,
+    /// Centro delle teste di tutti i player, presente solo a schermo unito con più player.
+    pub group_center: Option<Vec2>,
+}
+
+/// Con un solo player, o con tutti i player vicini, resta attiva solo la camera dello
+/// slot 0 a tutto schermo; altrimenti ogni camera ha il suo riquadro (metà o quarto).
+pub(super) fn update_split_screen(
+    window: Single<&Window, With<PrimaryWindow>>,
+    players: Query<&Head, With<Player>>,
+    heads: Query<&GlobalTransform>,
+    mut cameras: Query<(&mut Camera, &PlayerCamera)>,
+    mut split_screen: ResMut<SplitScreen>,
+) {
+    let positions: Vec<Vec2> = players
+        .iter()
+        .filter_map(|head| heads.get(head.0).ok())
+        .map(|head| head.translation().truncate())
+        .collect();
+    let spread = positions
+        .iter()
+        .flat_map(|a| positions.iter().map(move |b| a.distance(*b)))
+        .fold(0., f32::max);
+
+    let threshold = if split_screen.merged {
+        SPLIT_DISTANCE
+    } else {
+        MERGE_DISTANCE
+    };
+    split_screen.merged = spread < threshold;
+    split_screen.group_center = (split_screen.merged && positions.len() > 1)
+        .then(|| positions.iter().sum::<Vec2>() / positions.len() as f32);
+
+    let player_nums = cameras.iter().count();
+    let columns = if player_nums > 1 { 2 } else { 1 };
+    let rows = if player_nums > 2 { 2 } else { 1 };
+    let window_size = window.physical_size();
+    if window_size.min_element() == 0 {
+        return;
+    }
+    let cell_size = window_size / UVec2::new(columns, rows);
+
+    for (mut camera, slot) in &mut cameras {
+        if split_screen.merged {
+            camera.is_active = slot.0 == 0;
+            camera.viewport = None;
+            continue;
+        }
+        let cell = UVec2::new(slot.0 as u32 % columns, slot.0 as u32 / columns);
+        camera.is_active = true;
+        camera.viewport = Some(Viewport {
+            physical_position: cell * cell_size,
+            physical_size: cell_size,
+            ..default()
+        });
+    }
+}
@@ -0,0 +1,109 @@
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+use super::CameraSettings;
+
+/// Pixel di scorrimento (touchpad) equivalenti a uno scatto della rotella.
+const PIXELS_PER_LINE: f32 = 100.;
+
+/// Scala verso cui la camera converge, e il punto dello schermo che resta fermo
+/// mentre ci arriva (la posizione del cursore quando si usa la rotella).
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CameraZoom {
+    pub target: f32,
+    /// Scala richiesta dal framing in questo frame, prevale su `target` se presente.
+    pub(super) framed: Option<f32>,
+    /// Posizione del cursore rispetto al centro del viewport, in pixel con y verso l'alto.
+    anchor: Option<Vec2>,
+}
+
+impl CameraZoom {
+    pub fn new(scale: f32) -> Self {
+        Self {
+            target: scale,
+            framed: None,
+            anchor: None,
+        }
+    }
+}
+
+/// Rotella: zoom a scatti moltiplicativi verso il cursore, solo sulla camera sotto
+/// il cursore. Tastierino + e -: zoom continuo centrato.
+pub(super) fn zoom_input(
+    mut wheel: EventReader<MouseWheel>,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&Camera, &mut CameraZoom)>,
+    settings: Res<CameraSettings>,
+    time: Res<Time>,
+) {
+    let notches: f32 = wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        })
+        .sum();
+    let mut key_zoom = 0.;
+    if kb_input.pressed(KeyCode::NumpadAdd) {
+        key_zoom += 1.;
+    }
+    if kb_input.pressed(KeyCode::NumpadSubtract) {
+        key_zoom -= 1.;
+    }
+    let cursor = window.cursor_position();
+
+    for (camera, mut zoom) in &mut cameras {
+        if !camera.is_active {
+            continue;
+        }
+        if key_zoom != 0. {
+            zoom.target *= (key_zoom * settings.key_zoom_speed * time.delta_secs()).exp();
+            zoom.anchor = None;
+        }
+        if notches != 0. {
+            let hovered = cursor
+                .zip(camera.logical_viewport_rect())
+                .filter(|(cursor, viewport)| viewport.contains(*cursor));
+            if let Some((cursor, viewport)) = hovered {
+                zoom.target *= settings.wheel_zoom_step.powf(-notches);
+                zoom.anchor = Some((cursor - viewport.center()) * Vec2::new(1., -1.));
+            }
+        }
+        zoom.target = zoom.target.clamp(settings.min_scale, settings.max_scale);
+    }
+}
+
+/// Avvicina la scala a quella obiettivo in modo esponenziale (in scala logaritmica,
+/// così ogni raddoppio dura uguale) e indipendente dal frame rate.
+pub(super) fn apply_zoom(
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection, &mut CameraZoom)>,
+    settings: Res<CameraSettings>,
+    time: Res<Time>,
+) {
+    let blend = 1. - (-settings.zoom_smoothing * time.delta_secs()).exp();
+    for (mut transform, mut projection, mut zoom) in &mut cameras {
+        let old_scale = projection.scale.max(settings.min_scale);
+        let log_scale = old_scale.ln();
+        let goal = zoom
+            .framed
+            .unwrap_or(zoom.target)
+            .clamp(settings.min_scale, settings.max_scale);
+        let mut new_scale = (log_scale + (goal.ln() - log_scale) * blend).exp();
+        if (new_scale - goal).abs() <= goal * 1e-3 {
+            new_scale = goal;
+        }
+
+        // il punto di mondo sotto il cursore resta sotto il cursore
+        if let Some(anchor) = zoom.anchor {
+            transform.translation += (anchor * (old_scale - new_scale)).extend(0.);
+            if new_scale == goal {
+                zoom.anchor = None;
+            }
+        }
+        projection.scale = new_scale;
+    }
+}
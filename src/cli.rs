@@ -0,0 +1,182 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use clap::Parser;
+
+use crate::{
+    collision_log::CollisionLogPlugin,
+    game_state::GameRng,
+    player_plugin::ChainLength,
+    replay::{check_deterministic_build, Replay, ReplayMode, ReplayPlugin},
+    robot_factory::blueprint::{BlueprintPlugin, ExtraCreature, RobotBlueprint},
+    settings::{DisplayMode, GameSettings},
+    snapshot::{SnapshotPlugin, StartingLevel, WorldSnapshot},
+};
+
+/// Senza argomenti parte il gioco con le impostazioni salvate; gli argomenti cambiano
+/// solo questo avvio.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// Ogni partita parte da questo snapshot (F9 in gioco) invece che dal livello generato
+    #[arg(long, value_name = "SNAPSHOT")]
+    pub level: Option<PathBuf>,
+    /// Seed della casualità (terreno generato, `--evolve`): stesso livello a ogni partita
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Robot salvato dall'evoluzione (es. `evolution/best.ron`) da aggiungere alla partita
+    #[arg(long, value_name = "BLUEPRINT")]
+    pub creature: Option<PathBuf>,
+    /// Segmenti del corpo di ogni creatura
+    #[arg(long)]
+    pub segments: Option<usize>,
+    /// Giocatori locali a schermo diviso
+    #[arg(long, default_value_t = 1)]
+    pub players: usize,
+    /// Dimensioni della finestra, es. `1920x1080`
+    #[arg(long, value_name = "WxH", value_parser = parse_window_size)]
+    pub window: Option<UVec2>,
+    /// Schermo intero, ha la precedenza su `--window`
+    #[arg(long)]
+    pub fullscreen: bool,
+    /// Simula senza finestra né GPU questo numero di passi di fisica
+    #[arg(long, value_name = "STEPS", num_args = 0..=1, default_missing_value = "600")]
+    pub headless: Option<u32>,
+    /// Registra gli input della partita in questo file
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+    /// Rigioca una partita registrata con `--record`
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+    /// Scrive un evento di contatto per riga (JSON) in questo file
+    #[arg(long, value_name = "PATH")]
+    pub collision_log: Option<PathBuf>,
+    /// Parte con collider, joint e perf UI visibili (serve `--features debug`)
+    #[arg(long)]
+    pub debug: bool,
+    /// Evolve robot senza finestra, salvandoli in `evolution/`
+    #[arg(long)]
+    pub evolve: bool,
+    /// Misura fisica e frame al crescere di catena e terreno, CSV nel file o sullo stdout
+    #[arg(long, value_name = "CSV")]
+    pub bench: Option<Option<PathBuf>>,
+    /// Confronta il rendering del bloom con le immagini in `tests/golden`
+    #[arg(long)]
+    pub golden: bool,
+    /// Riscrive le immagini in `tests/golden`
+    #[arg(long)]
+    pub golden_update: bool,
+}
+
+fn parse_window_size(size: &str) -> Result<UVec2, String> {
+    let (width, height) = size
+        .split_once('x')
+        .ok_or_else(|| format!("{size}: expected WxH, e.g. 1280x720"))?;
+    let parse = |value: &str| {
+        value
+            .parse::<u32>()
+            .map_err(|err| format!("{value}: {err}"))
+    };
+    Ok(UVec2::new(parse(width)?, parse(height)?))
+}
+
+impl Cli {
+    /// Finestra richiesta dagli argomenti, sopra a quella delle impostazioni.
+    pub fn apply_window(&self, settings: &mut GameSettings) {
+        if let Some(size) = self.window {
+            settings.resolution = size;
+            settings.display_mode = DisplayMode::Windowed;
+        }
+        if self.fullscreen {
+            settings.display_mode = DisplayMode::Fullscreen;
+        }
+    }
+
+    /// Legge i file indicati dagli argomenti; il primo che manca o non è valido
+    /// interrompe l'avvio.
+    pub fn scenario(&self) -> Result<Scenario, String> {
+        let level = match &self.level {
+            Some(path) => Some(
+                WorldSnapshot::load(path)
+                    .map_err(|err| format!("could not load level {}: {err}", path.display()))?,
+            ),
+            None => None,
+        };
+        let creature = match &self.creature {
+            Some(path) => Some(
+                RobotBlueprint::load(path)
+                    .map_err(|err| format!("could not load creature {}: {err}", path.display()))?,
+            ),
+            None => None,
+        };
+        if self.replay.is_some() || self.record.is_some() {
+            check_deterministic_build()?;
+        }
+        let replay = match (&self.replay, &self.record) {
+            (Some(path), _) => {
+                Some(ReplayMode::Play(Replay::load(path).map_err(|err| {
+                    format!("could not load replay {}: {err}", path.display())
+                })?))
+            }
+            (None, Some(path)) => Some(ReplayMode::Record(path.clone())),
+            (None, None) => None,
+        };
+        Ok(Scenario {
+            seed: self.seed,
+            segments: self.segments,
+            level,
+            creature,
+            replay,
+            collision_log: self.collision_log.clone(),
+        })
+    }
+}
+
+/// Quello che gli argomenti cambiano nella partita, uguale con la finestra e con
+/// `--headless`.
+pub struct Scenario {
+    pub seed: Option<u64>,
+    pub segments: Option<usize>,
+    pub level: Option<WorldSnapshot>,
+    pub creature: Option<RobotBlueprint>,
+    pub replay: Option<ReplayMode>,
+    pub collision_log: Option<PathBuf>,
+}
+
+impl Scenario {
+    /// Giocatori della partita: un replay impone i suoi.
+    pub fn players(&self, requested: usize) -> usize {
+        match &self.replay {
+            Some(ReplayMode::Play(replay)) => replay.players,
+            _ => requested,
+        }
+    }
+
+    pub fn configure(self, app: &mut App) {
+        if let Some(seed) = self.seed {
+            app.insert_resource(GameRng::new(seed));
+        }
+        if let Some(segments) = self.segments {
+            app.insert_resource(ChainLength(segments));
+        }
+        if let Some(level) = self.level {
+            if !app.is_plugin_added::<SnapshotPlugin>() {
+                app.add_plugins(SnapshotPlugin);
+            }
+            app.insert_resource(StartingLevel(level));
+        }
+        if let Some(creature) = self.creature {
+            if !app.is_plugin_added::<BlueprintPlugin>() {
+                app.add_plugins(BlueprintPlugin);
+            }
+            app.insert_resource(ExtraCreature(creature));
+        }
+        // dopo il seed: un replay usa quello con cui è stato registrato
+        if let Some(mode) = self.replay {
+            app.add_plugins(ReplayPlugin { mode });
+        }
+        if let Some(path) = self.collision_log {
+            app.add_plugins(CollisionLogPlugin { output: Some(path) });
+        }
+    }
+}
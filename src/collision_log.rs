@@ -0,0 +1,266 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use bevy::{core::FrameCount, prelude::*, utils::HashMap};
+use bevy_rapier2d::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    robot_factory::robot_parts::{PartOf, RobotBody, RobotHead},
+    terrain_plugin::Cube,
+};
+
+/// Coppie stampate nel riepilogo (F7 e all'uscita).
+const SUMMARY_PAIRS: usize = 10;
+
+/// Registra collisioni e forze di contatto con etichette leggibili (`RobotBody#37`,
+/// `Cube#4`), tiene le statistiche per coppia e, se `output` è presente, scrive ogni
+/// evento come una riga JSON.
+pub struct CollisionLogPlugin {
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum PartKind {
+    Head,
+    Body,
+    Terrain,
+    Other,
+}
+
+/// Quali eventi registrare. Liste vuote vuol dire nessun filtro; basta che uno dei
+/// due corpi passi il filtro.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct CollisionFilter {
+    pub kinds: Vec<PartKind>,
+    pub entities: Vec<Entity>,
+    /// Le forze di contatto sotto questa soglia vengono ignorate.
+    pub min_force: f32,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PairStats {
+    pub a: String,
+    pub b: String,
+    pub started: u32,
+    pub stopped: u32,
+    pub force_events: u32,
+    pub peak_force: f32,
+    pub total_force: f32,
+}
+
+/// Statistiche per coppia di entità, con la coppia in ordine di indice.
+#[derive(Resource, Default)]
+pub struct CollisionStats {
+    pub pairs: HashMap<(Entity, Entity), PairStats>,
+}
+
+#[derive(Resource)]
+struct CollisionLogFile(Option<BufWriter<File>>);
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordKind {
+    Started,
+    Stopped,
+    Force,
+}
+
+#[derive(Serialize)]
+struct CollisionRecord<'a> {
+    frame: u32,
+    time: f32,
+    event: RecordKind,
+    a: &'a str,
+    b: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    force: Option<f32>,
+}
+
+impl Plugin for CollisionLogPlugin {
+    fn build(&self, app: &mut App) {
+        let file = self.output.as_ref().and_then(|path| {
+            File::create(path)
+                .map(BufWriter::new)
+                .map_err(|err| println!("could not create {}: {err}", path.display()))
+                .ok()
+        });
+        app.init_resource::<CollisionFilter>()
+            .init_resource::<CollisionStats>()
+            .insert_resource(CollisionLogFile(file))
+            .add_systems(
+                Update,
+                (enable_collision_events, log_collisions, summary_hotkey),
+            )
+            .add_systems(Last, finish_log);
+    }
+}
+
+/// Le creature generano `CollisionEvent` solo se lo chiedono: il flag si aggiunge a
+/// quelli già presenti (es. le forze di contatto dello shake), in qualunque ordine
+/// arrivino i comandi.
+fn enable_collision_events(mut commands: Commands, new_parts: Query<Entity, Added<PartOf>>) {
+    for part in &new_parts {
+        commands
+            .entity(part)
+            .entry::<ActiveEvents>()
+            .and_modify(|mut events| *events |= ActiveEvents::COLLISION_EVENTS)
+            .or_insert(ActiveEvents::COLLISION_EVENTS);
+    }
+}
+
+fn part_kind(is_head: bool, is_body: bool, is_cube: bool) -> PartKind {
+    match (is_head, is_body, is_cube) {
+        (true, _, _) => PartKind::Head,
+        (_, true, _) => PartKind::Body,
+        (_, _, true) => PartKind::Terrain,
+        _ => PartKind::Other,
+    }
+}
+
+fn label(entity: Entity, kind: PartKind) -> String {
+    let name = match kind {
+        PartKind::Head => "RobotHead",
+        PartKind::Body => "RobotBody",
+        PartKind::Terrain => "Cube",
+        PartKind::Other => "Entity",
+    };
+    format!("{name}#{}", entity.index())
+}
+
+fn log_collisions(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut contact_force_events: EventReader<ContactForceEvent>,
+    kinds: Query<(Has<RobotHead>, Has<RobotBody>, Has<Cube>)>,
+    filter: Res<CollisionFilter>,
+    mut stats: ResMut<CollisionStats>,
+    mut file: ResMut<CollisionLogFile>,
+    frame: Res<FrameCount>,
+    time: Res<Time>,
+) {
+    let kind_of = |entity: Entity| {
+        kinds
+            .get(entity)
+            .map_or(PartKind::Other, |(head, body, cube)| {
+                part_kind(head, body, cube)
+            })
+    };
+    let events = collision_events
+        .read()
+        .map(|event| match *event {
+            CollisionEvent::Started(a, b, _) => (a, b, RecordKind::Started, None),
+            CollisionEvent::Stopped(a, b, _) => (a, b, RecordKind::Stopped, None),
+        })
+        .chain(contact_force_events.read().map(|event| {
+            (
+                event.collider1,
+                event.collider2,
+                RecordKind::Force,
+                Some(event.total_force_magnitude),
+            )
+        }));
+
+    for (a, b, event, force) in events {
+        let (a, b) = if a.index() <= b.index() {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let (kind_a, kind_b) = (kind_of(a), kind_of(b));
+        let kind_ok = filter.kinds.is_empty()
+            || filter.kinds.contains(&kind_a)
+            || filter.kinds.contains(&kind_b);
+        let entity_ok = filter.entities.is_empty()
+            || filter.entities.contains(&a)
+            || filter.entities.contains(&b);
+        let force_ok = force.map_or(true, |force| force >= filter.min_force);
+        if !(kind_ok && entity_ok && force_ok) {
+            continue;
+        }
+
+        let pair = stats.pairs.entry((a, b)).or_insert_with(|| PairStats {
+            a: label(a, kind_a),
+            b: label(b, kind_b),
+            ..default()
+        });
+        match event {
+            RecordKind::Started => pair.started += 1,
+            RecordKind::Stopped => pair.stopped += 1,
+            RecordKind::Force => {
+                let force = force.unwrap_or_default();
+                pair.force_events += 1;
+                pair.peak_force = pair.peak_force.max(force);
+                pair.total_force += force;
+            }
+        }
+
+        if let Some(writer) = &mut file.0 {
+            let record = CollisionRecord {
+                frame: frame.0,
+                time: time.elapsed_secs(),
+                event,
+                a: &pair.a,
+                b: &pair.b,
+                force,
+            };
+            let written = serde_json::to_writer(&mut *writer, &record)
+                .map_err(std::io::Error::from)
+                .and_then(|_| writeln!(writer));
+            if let Err(err) = written {
+                println!("could not write collision log: {err}");
+                file.0 = None;
+            }
+        }
+    }
+}
+
+impl CollisionStats {
+    /// Le coppie con la forza di picco più alta, in ordine decrescente.
+    pub fn strongest(&self, count: usize) -> Vec<&PairStats> {
+        let mut pairs: Vec<&PairStats> = self.pairs.values().collect();
+        pairs.sort_by(|a, b| b.peak_force.total_cmp(&a.peak_force));
+        pairs.truncate(count);
+        pairs
+    }
+
+    fn print_summary(&self) {
+        println!("collisions: {} pairs", self.pairs.len());
+        for pair in self.strongest(SUMMARY_PAIRS) {
+            println!(
+                "  {} <-> {}: {} contacts, {} force events, peak {:.0}, mean {:.0}",
+                pair.a,
+                pair.b,
+                pair.started,
+                pair.force_events,
+                pair.peak_force,
+                pair.total_force / pair.force_events.max(1) as f32,
+            );
+        }
+    }
+}
+
+fn summary_hotkey(kb_input: Res<ButtonInput<KeyCode>>, stats: Res<CollisionStats>) {
+    if kb_input.just_pressed(KeyCode::F7) {
+        stats.print_summary();
+    }
+}
+
+/// All'uscita stampa il riepilogo e svuota il buffer del file.
+fn finish_log(
+    mut exit: EventReader<AppExit>,
+    stats: Res<CollisionStats>,
+    mut file: ResMut<CollisionLogFile>,
+) {
+    if exit.read().next().is_none() {
+        return;
+    }
+    stats.print_summary();
+    if let Some(writer) = &mut file.0 {
+        if let Err(err) = writer.flush() {
+            println!("could not write collision log: {err}");
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use std::{fs, path::PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Cartella dei file di configurazione, es. `~/.config/bloom_game` su Linux.
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("bloom_game"))
+}
+
+/// Legge `file_name` dalla cartella di configurazione; se manca o non è valido
+/// ritorna i valori di default.
+pub fn load_config<T: DeserializeOwned + Default>(file_name: &str) -> T {
+    let Some(path) = config_dir().map(|dir| dir.join(file_name)) else {
+        return T::default();
+    };
+    let Ok(text) = fs::read_to_string(&path) else {
+        return T::default();
+    };
+    ron::from_str(&text).unwrap_or_else(|err| {
+        println!("invalid config {}: {err}", path.display());
+        T::default()
+    })
+}
+
+pub fn save_config<T: Serialize>(file_name: &str, value: &T) {
+    let Some(dir) = config_dir() else {
+        return;
+    };
+    let result = fs::create_dir_all(&dir)
+        .map_err(ron::Error::from)
+        .and_then(|_| ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()))
+        .and_then(|text| fs::write(dir.join(file_name), text).map_err(ron::Error::from));
+    if let Err(err) = result {
+        println!("could not save config {file_name}: {err}");
+    }
+}
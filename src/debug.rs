@@ -0,0 +1,125 @@
+use bevy::{
+    diagnostic::{
+        EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin,
+        SystemInformationDiagnosticsPlugin,
+    },
+    prelude::*,
+};
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use bevy_rapier2d::prelude::*;
+use iyes_perf_ui::prelude::{PerfUiAllEntries, PerfUiEntryEntityCount, PerfUiRoot};
+use iyes_perf_ui::{PerfUiPlugin, PerfUiSet};
+
+/// Strumenti di debug attivi, tutti spenti all'avvio (con `--debug` collider, joint
+/// e perf UI partono accesi).
+/// F3 collider, F4 joint, F5 conteggio entità, F6 inspector, F12 perf UI.
+#[derive(Resource, Default, Debug)]
+pub struct DebugOverlays {
+    pub colliders: bool,
+    pub joints: bool,
+    pub entity_count: bool,
+    pub inspector: bool,
+    pub perf_ui: bool,
+}
+
+/// Pannello del perf UI completo.
+#[derive(Component)]
+struct DebugPerfUi;
+
+/// Pannello col solo conteggio delle entità.
+#[derive(Component)]
+struct DebugEntityCount;
+
+pub struct DebugPlugin;
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        app.init_resource::<DebugOverlays>()
+            .add_plugins((
+                RapierDebugRenderPlugin {
+                    enabled: false,
+                    ..default()
+                },
+                SystemInformationDiagnosticsPlugin,
+                EntityCountDiagnosticsPlugin,
+                PerfUiPlugin,
+                WorldInspectorPlugin::new()
+                    .run_if(|overlays: Res<DebugOverlays>| overlays.inspector),
+            ))
+            .add_systems(
+                Update,
+                (
+                    debug_hotkeys,
+                    (apply_debug_render, toggle_perf_ui).run_if(resource_changed::<DebugOverlays>),
+                )
+                    .chain()
+                    .before(PerfUiSet::Setup),
+            );
+    }
+}
+
+fn debug_hotkeys(kb_input: Res<ButtonInput<KeyCode>>, mut overlays: ResMut<DebugOverlays>) {
+    let keys = [
+        KeyCode::F3,
+        KeyCode::F4,
+        KeyCode::F5,
+        KeyCode::F6,
+        KeyCode::F12,
+    ];
+    // la risorsa va toccata solo se serve, altrimenti risulterebbe cambiata ogni frame
+    if !kb_input.any_just_pressed(keys) {
+        return;
+    }
+    let toggle = |key: KeyCode, flag: &mut bool| {
+        if kb_input.just_pressed(key) {
+            *flag = !*flag;
+        }
+    };
+    toggle(KeyCode::F3, &mut overlays.colliders);
+    toggle(KeyCode::F4, &mut overlays.joints);
+    toggle(KeyCode::F5, &mut overlays.entity_count);
+    toggle(KeyCode::F6, &mut overlays.inspector);
+    toggle(KeyCode::F12, &mut overlays.perf_ui);
+}
+
+/// Collider e joint vengono dallo stesso render di debug di Rapier: si accende se
+/// almeno uno dei due è richiesto.
+fn apply_debug_render(overlays: Res<DebugOverlays>, mut context: ResMut<DebugRenderContext>) {
+    let mut mode = DebugRenderMode::empty();
+    if overlays.colliders {
+        mode |= DebugRenderMode::COLLIDER_SHAPES;
+    }
+    if overlays.joints {
+        mode |= DebugRenderMode::JOINTS;
+    }
+    context.pipeline.mode = mode;
+    context.enabled = !mode.is_empty();
+}
+
+fn toggle_perf_ui(
+    mut commands: Commands,
+    overlays: Res<DebugOverlays>,
+    perf_ui: Query<Entity, With<DebugPerfUi>>,
+    entity_count: Query<Entity, With<DebugEntityCount>>,
+) {
+    match (overlays.perf_ui, perf_ui.get_single()) {
+        (true, Err(_)) => {
+            commands.spawn((DebugPerfUi, PerfUiAllEntries::default()));
+        }
+        (false, Ok(root)) => commands.entity(root).despawn_recursive(),
+        _ => {}
+    }
+    match (overlays.entity_count, entity_count.get_single()) {
+        (true, Err(_)) => {
+            commands.spawn((
+                DebugEntityCount,
+                PerfUiRoot::default(),
+                PerfUiEntryEntityCount::default(),
+            ));
+        }
+        (false, Ok(root)) => commands.entity(root).despawn_recursive(),
+        _ => {}
+    }
+}
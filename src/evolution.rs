@@ -0,0 +1,230 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    game_state::GameRng,
+    headless::{headless_app, PHYSICS_DT},
+    mechanical_components::generic::MyPosition,
+    robot_factory::blueprint::{spawn_blueprint, BlueprintPlugin, RobotBlueprint},
+    terrain_plugin::TerrainPlugin,
+};
+
+/// Poco sopra la fila di cubi di `spawn_terrain`.
+const SPAWN_POSITION: MyPosition = MyPosition { x: 0., y: -750. };
+
+#[derive(Resource, Clone)]
+pub struct EvolutionConfig {
+    pub population: usize,
+    pub generations: usize,
+    /// Secondi simulati per ogni individuo.
+    pub eval_seconds: f32,
+    pub mutation_rate: f64,
+    /// Migliori individui copiati invariati nella generazione successiva.
+    pub elite: usize,
+    pub tournament_size: usize,
+    pub seed: u64,
+    pub output_dir: PathBuf,
+}
+
+impl Default for EvolutionConfig {
+    fn default() -> Self {
+        Self {
+            population: 30,
+            generations: 20,
+            eval_seconds: 10.,
+            mutation_rate: 0.15,
+            elite: 2,
+            tournament_size: 3,
+            seed: 42,
+            output_dir: PathBuf::from("evolution"),
+        }
+    }
+}
+
+impl EvolutionConfig {
+    /// Valori con cui la selezione non può funzionare (es. un torneo senza sfidanti).
+    fn validate(&self) -> Result<(), String> {
+        if self.population == 0 {
+            return Err("population must be at least 1".into());
+        }
+        if self.tournament_size == 0 {
+            return Err("tournament_size must be at least 1".into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+struct Population {
+    blueprints: Vec<RobotBlueprint>,
+    fitness: Vec<f32>,
+    generation: usize,
+    rng: StdRng,
+}
+
+/// Individuo attualmente in simulazione.
+#[derive(Resource, Default)]
+struct Trial(Option<RunningTrial>);
+
+struct RunningTrial {
+    robot: Entity,
+    head: Entity,
+    start_x: Option<f32>,
+    steps: u32,
+}
+
+/// Avvia la modalità evoluzione senza finestra né rendering: gli individui vengono
+/// simulati uno alla volta sul terreno di `TerrainPlugin`, alla massima velocità possibile.
+pub fn run(config: EvolutionConfig) -> AppExit {
+    if let Err(err) = config.validate() {
+        println!("invalid evolution config: {err}");
+        return AppExit::error();
+    }
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let population = Population {
+        blueprints: (0..config.population)
+            .map(|_| RobotBlueprint::random(&mut rng))
+            .collect(),
+        fitness: vec![],
+        generation: 0,
+        rng,
+    };
+
+    headless_app()
+        .add_plugins((TerrainPlugin, BlueprintPlugin))
+        .insert_resource(population)
+        // stesso terreno per tutti gli individui di una run
+        .insert_resource(GameRng::new(config.seed))
+        .insert_resource(config)
+        .init_resource::<Trial>()
+        .add_systems(Update, run_trials)
+        .run()
+}
+
+fn run_trials(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut population: ResMut<Population>,
+    mut trial: ResMut<Trial>,
+    config: Res<EvolutionConfig>,
+    transforms: Query<&Transform>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let Some(running) = trial.0.as_mut() else {
+        let index = population.fitness.len();
+        let spawned = spawn_blueprint(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &population.blueprints[index],
+            SPAWN_POSITION,
+        );
+        // nel gioco i pezzi fluttuano, qui devono camminare sul terreno
+        for &part in &spawned.parts {
+            commands.entity(part).insert(GravityScale(1.));
+        }
+        trial.0 = Some(RunningTrial {
+            robot: spawned.robot,
+            head: spawned.parts[0],
+            start_x: None,
+            steps: 0,
+        });
+        return;
+    };
+
+    let Ok(head) = transforms.get(running.head) else {
+        return;
+    };
+    let x = head.translation.x;
+    let start_x = *running.start_x.get_or_insert(x);
+    running.steps += 1;
+    if (running.steps as f32) * PHYSICS_DT < config.eval_seconds {
+        return;
+    }
+
+    // fitness: distanza orizzontale percorsa dalla testa
+    let fitness = (x - start_x).abs();
+    population
+        .fitness
+        .push(if fitness.is_finite() { fitness } else { 0. });
+    commands.entity(running.robot).despawn_recursive();
+    trial.0 = None;
+
+    if population.fitness.len() < population.blueprints.len() {
+        return;
+    }
+    finish_generation(&mut population, &config);
+    if population.generation >= config.generations {
+        exit.send(AppExit::Success);
+    }
+}
+
+/// Salva il migliore della generazione e costruisce la successiva con elitismo,
+/// selezione a torneo, crossover e mutazione.
+fn finish_generation(population: &mut Population, config: &EvolutionConfig) {
+    let Population {
+        blueprints,
+        fitness,
+        generation,
+        rng,
+    } = population;
+
+    let mut ranking: Vec<usize> = (0..blueprints.len()).collect();
+    ranking.sort_by(|&a, &b| fitness[b].total_cmp(&fitness[a]));
+    let best = ranking[0];
+    let mean = fitness.iter().sum::<f32>() / fitness.len() as f32;
+    println!(
+        "generation {:>3}: best {:>8.1}  mean {:>8.1}  parts {}",
+        generation,
+        fitness[best],
+        mean,
+        blueprints[best].parts.len()
+    );
+    if let Err(err) = save_blueprint(
+        config,
+        &format!("gen_{:03}.ron", generation),
+        &blueprints[best],
+    )
+    .and_then(|_| save_blueprint(config, "best.ron", &blueprints[best]))
+    {
+        println!("could not save the best blueprint: {err}");
+    }
+
+    let tournament = |rng: &mut StdRng| {
+        (0..config.tournament_size)
+            .map(|_| rng.gen_range(0..blueprints.len()))
+            .max_by(|&a, &b| fitness[a].total_cmp(&fitness[b]))
+            .unwrap()
+    };
+    let mut next: Vec<RobotBlueprint> = ranking
+        .iter()
+        .take(config.elite)
+        .map(|&i| blueprints[i].clone())
+        .collect();
+    while next.len() < blueprints.len() {
+        let mother = tournament(&mut *rng);
+        let father = tournament(&mut *rng);
+        let mut child = blueprints[mother].crossover(&blueprints[father], rng);
+        child.mutate(rng, config.mutation_rate);
+        next.push(child);
+    }
+
+    *blueprints = next;
+    fitness.clear();
+    *generation += 1;
+}
+
+fn save_blueprint(
+    config: &EvolutionConfig,
+    file_name: &str,
+    blueprint: &RobotBlueprint,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(&config.output_dir)?;
+    let ron = ron::ser::to_string_pretty(blueprint, ron::ser::PrettyConfig::default())?;
+    fs::write(config.output_dir.join(file_name), ron)?;
+    Ok(())
+}
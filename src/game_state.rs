@@ -0,0 +1,296 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera_plugin::PhotoMode, player_plugin::Player, robot_factory::robot_parts::Head,
+    terrain_plugin::LevelBounds,
+};
+
+/// Sotto il fondo del livello di questa distanza una creatura è persa.
+const FALL_MARGIN: f32 = 2_000.;
+
+#[derive(States, Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum GameState {
+    /// Primo frame: niente è ancora pronto.
+    #[default]
+    Boot,
+    MainMenu,
+    Playing,
+    /// Fisica e tempo virtuale fermi (Esc).
+    Paused,
+    /// Tutte le creature sono cadute fuori dal livello.
+    GameOver,
+}
+
+/// Una partita in corso, anche in pausa o finita: terreno e creature vivono finché
+/// si resta qui, e vengono eliminati (vedi `StateScoped`) tornando al menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InRun;
+
+impl ComputedStates for InRun {
+    type SourceStates = GameState;
+
+    fn compute(state: GameState) -> Option<Self> {
+        matches!(
+            state,
+            GameState::Playing | GameState::Paused | GameState::GameOver
+        )
+        .then_some(InRun)
+    }
+}
+
+/// Unica sorgente di casualità del gioco, riseminata con `seed` a ogni partita:
+/// con lo stesso seed (e gli stessi input, vedi `replay`) la partita si ripete uguale.
+#[derive(Resource)]
+pub struct GameRng {
+    pub seed: u64,
+    pub rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Self::new(seed)
+    }
+}
+
+/// Ricominciare vuol dire uscire da [`InRun`] e rientrarci: si passa dal menu per
+/// un frame senza mostrarlo.
+#[derive(Resource, Default)]
+struct RestartRun(bool);
+
+pub struct GameStatePlugin;
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<GameState>()
+            .add_computed_state::<InRun>()
+            .enable_state_scoped_entities::<GameState>()
+            .enable_state_scoped_entities::<InRun>()
+            .init_resource::<RestartRun>()
+            .init_resource::<GameRng>()
+            // a fine partita, così la prossima ripete la sequenza dall'inizio
+            .add_systems(OnExit(InRun), reseed_rng)
+            .add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
+            .add_systems(OnEnter(GameState::Paused), (freeze_world, spawn_pause_menu))
+            .add_systems(OnExit(GameState::Paused), thaw_world)
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                (freeze_world, spawn_game_over),
+            )
+            .add_systems(OnExit(GameState::GameOver), thaw_world)
+            .add_systems(
+                Update,
+                (
+                    finish_boot.run_if(in_state(GameState::Boot)),
+                    main_menu_input.run_if(in_state(GameState::MainMenu)),
+                    (pause_input, check_game_over).run_if(in_state(GameState::Playing)),
+                    paused_input.run_if(in_state(GameState::Paused)),
+                    game_over_input.run_if(in_state(GameState::GameOver)),
+                ),
+            );
+    }
+}
+
+fn reseed_rng(mut rng: ResMut<GameRng>) {
+    *rng = GameRng::new(rng.seed);
+}
+
+fn finish_boot(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::MainMenu);
+}
+
+fn freeze_world(
+    mut rapier_config: Query<&mut RapierConfiguration>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    for mut config in &mut rapier_config {
+        config.physics_pipeline_active = false;
+    }
+    time.pause();
+}
+
+fn thaw_world(mut rapier_config: Query<&mut RapierConfiguration>, mut time: ResMut<Time<Virtual>>) {
+    for mut config in &mut rapier_config {
+        config.physics_pipeline_active = true;
+    }
+    time.unpause();
+}
+
+/// Schermata a tutto schermo con un titolo e i tasti disponibili, eliminata
+/// all'uscita da `state`.
+fn spawn_overlay(commands: &mut Commands, state: GameState, title: &str, hint: &str) {
+    commands
+        .spawn((
+            StateScoped(state),
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(24.),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0., 0., 0., 0.6)),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(title),
+                TextFont {
+                    font_size: 72.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.6, 0.4, 1.)),
+            ));
+            parent.spawn((
+                Text::new(hint),
+                TextFont {
+                    font_size: 28.,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn spawn_main_menu(
+    mut commands: Commands,
+    mut restart: ResMut<RestartRun>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if restart.0 {
+        restart.0 = false;
+        next_state.set(GameState::Playing);
+        return;
+    }
+    spawn_overlay(
+        &mut commands,
+        GameState::MainMenu,
+        "Bloom Snake",
+        "Invio: gioca    F1: impostazioni    Esc: esci",
+    );
+}
+
+fn spawn_pause_menu(mut commands: Commands) {
+    spawn_overlay(
+        &mut commands,
+        GameState::Paused,
+        "Pausa",
+        "Esc: riprendi    R: ricomincia    Q: menu    F1: impostazioni",
+    );
+}
+
+fn spawn_game_over(mut commands: Commands) {
+    spawn_overlay(
+        &mut commands,
+        GameState::GameOver,
+        "Game over",
+        "Invio: ricomincia    Q: menu",
+    );
+}
+
+fn main_menu_input(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let start = kb_input.just_pressed(KeyCode::Enter)
+        || gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(GamepadButton::Start));
+    if start {
+        next_state.set(GameState::Playing);
+    } else if kb_input.just_pressed(KeyCode::Escape) {
+        exit.send(AppExit::Success);
+    }
+}
+
+/// In modalità foto Esc non mette in pausa: la fisica è già ferma e la gestisce lei.
+fn pause_input(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    photo_mode: Res<PhotoMode>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let pause = kb_input.just_pressed(KeyCode::Escape)
+        || gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(GamepadButton::Start));
+    if pause && !photo_mode.active {
+        next_state.set(GameState::Paused);
+    }
+}
+
+fn paused_input(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut restart: ResMut<RestartRun>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let resume = kb_input.just_pressed(KeyCode::Escape)
+        || gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(GamepadButton::Start));
+    if resume {
+        next_state.set(GameState::Playing);
+    } else if kb_input.just_pressed(KeyCode::KeyR) {
+        restart.0 = true;
+        next_state.set(GameState::MainMenu);
+    } else if kb_input.just_pressed(KeyCode::KeyQ) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn game_over_input(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut restart: ResMut<RestartRun>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let again = kb_input.just_pressed(KeyCode::Enter)
+        || gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(GamepadButton::Start));
+    if again {
+        restart.0 = true;
+        next_state.set(GameState::MainMenu);
+    } else if kb_input.just_pressed(KeyCode::KeyQ) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+/// La partita finisce quando tutte le teste sono cadute sotto il livello.
+fn check_game_over(
+    players: Query<&Head, With<Player>>,
+    heads: Query<&GlobalTransform>,
+    level: Option<Res<LevelBounds>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(level) = level else {
+        return;
+    };
+    let floor = level.0.min.y - FALL_MARGIN;
+    let mut heights = players
+        .iter()
+        .filter_map(|head| heads.get(head.0).ok())
+        .map(|head| head.translation().y)
+        .peekable();
+    if heights.peek().is_some() && heights.all(|y| y < floor) {
+        next_state.set(GameState::GameOver);
+    }
+}
@@ -0,0 +1,310 @@
+//! Immagini di riferimento del bloom, renderizzate senza finestra. La CI, senza GPU,
+//! usa il driver Vulkan software lavapipe (`mesa-vulkan-drivers` su Debian/Ubuntu):
+//!
+//! ```sh
+//! VK_ICD_FILENAMES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json \
+//!     cargo test --release golden -- --ignored
+//! ```
+//!
+//! Con lo stesso driver `cargo run --release -- --golden-update` riscrive le immagini
+//! in `tests/golden`, da committare insieme al cambio che le ha rese diverse.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{
+    core_pipeline::{bloom::Bloom, tonemapping::Tonemapping},
+    log::LogPlugin,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        pipelined_rendering::PipelinedRenderingPlugin,
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        settings::{Backends, RenderCreation, WgpuSettings},
+        view::screenshot::{Screenshot, ScreenshotCaptured},
+        RenderPlugin,
+    },
+    time::TimeUpdateStrategy,
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use bevy_rapier2d::prelude::*;
+use image::RgbImage;
+
+use crate::{
+    camera_plugin::{BloomPreset, BloomSettings},
+    game_state::{GameRng, GameState, InRun},
+    headless::PHYSICS_DT,
+    mechanical_components::joints::JointsPlugin,
+    player_plugin::PlayerPlugin,
+    terrain_plugin::TerrainPlugin,
+    MyTimer, PIXELS_PER_METER,
+};
+
+/// Dimensione delle immagini renderizzate e di quelle di riferimento.
+const IMAGE_SIZE: UVec2 = UVec2::new(640, 360);
+/// Camera fissa: la testa, l'inizio della coda e il terreno sotto.
+const CAMERA_POSITION: Vec2 = Vec2::new(600., -300.);
+const CAMERA_SCALE: f32 = 6.;
+/// Seed del terreno: le immagini di riferimento valgono solo per questo livello.
+const GOLDEN_SEED: u64 = 0;
+/// Frame renderizzati prima della cattura, per avere tutte le pipeline pronte.
+const WARMUP_FRAMES: u32 = 10;
+/// Frame di attesa massima per la cattura, che arriva in modo asincrono.
+const CAPTURE_FRAMES: u32 = 30;
+/// Differenza per canale (0-255) sotto la quale due pixel sono considerati uguali:
+/// copre gli arrotondamenti tra versioni diverse del renderer software.
+const CHANNEL_TOLERANCE: u8 = 6;
+/// Frazione di pixel diversi oltre la quale il confronto fallisce.
+const MAX_DIFFERENT_PIXELS: f32 = 0.002;
+
+/// Una scena di riferimento: sempre la stessa creatura e lo stesso terreno, con un
+/// bloom diverso.
+pub struct GoldenScene {
+    pub name: &'static str,
+    pub bloom: BloomSettings,
+}
+
+pub fn scenes() -> Vec<GoldenScene> {
+    vec![
+        GoldenScene {
+            name: "bloom_default",
+            bloom: BloomSettings::default(),
+        },
+        GoldenScene {
+            name: "bloom_neon",
+            bloom: BloomSettings::preset(BloomPreset::Neon),
+        },
+        GoldenScene {
+            name: "bloom_dreamy",
+            bloom: BloomSettings::preset(BloomPreset::Dreamy),
+        },
+    ]
+}
+
+/// Immagini di riferimento, da aggiornare con `--golden-update` quando un cambio di
+/// colori o di bloom è voluto.
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// Dove finiscono i render che non corrispondono, per confrontarli a mano.
+fn failed_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("target/golden")
+}
+
+#[derive(Resource, Default)]
+struct CapturedImage(Option<Image>);
+
+/// Renderizza `bloom` senza finestra su un'immagine. Su una macchina senza GPU serve
+/// un driver Vulkan software, es. lavapipe (`mesa-vulkan-drivers` su Debian/Ubuntu).
+pub fn render_scene(bloom: &BloomSettings) -> Result<RgbImage, String> {
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .set(RenderPlugin {
+                render_creation: RenderCreation::Automatic(WgpuSettings {
+                    backends: Some(Backends::VULKAN),
+                    ..default()
+                }),
+                synchronous_pipeline_compilation: true,
+            })
+            .disable::<WinitPlugin>()
+            // ogni `app.update()` renderizza il suo frame, non quello prima
+            .disable::<PipelinedRenderingPlugin>()
+            .disable::<LogPlugin>(),
+    )
+    .insert_state(GameState::Playing)
+    .add_computed_state::<InRun>()
+    .insert_resource(MyTimer(Timer::from_seconds(
+        std::f32::consts::TAU,
+        TimerMode::Repeating,
+    )))
+    .insert_resource(ClearColor(Color::BLACK))
+    .init_resource::<CapturedImage>()
+    .insert_resource(GameRng::new(GOLDEN_SEED))
+    .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(
+        PIXELS_PER_METER,
+    ))
+    // a passo fisso la posa della catena non dipende da quanto ci mette ogni frame
+    .insert_resource(TimestepMode::Fixed {
+        dt: PHYSICS_DT,
+        substeps: 1,
+    })
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+        PHYSICS_DT,
+    )))
+    .add_plugins((PlayerPlugin, TerrainPlugin, JointsPlugin));
+    app.finish();
+    app.cleanup();
+
+    let target = app
+        .world_mut()
+        .resource_mut::<Assets<Image>>()
+        .add(render_target());
+    let mut bloom_component = Bloom::default();
+    let mut tonemapping = Tonemapping::default();
+    bloom.apply(&mut bloom_component, &mut tonemapping);
+    app.world_mut().spawn((
+        Camera2d,
+        Camera {
+            hdr: true,
+            target: RenderTarget::Image(target.clone()),
+            ..default()
+        },
+        OrthographicProjection {
+            scale: CAMERA_SCALE,
+            ..OrthographicProjection::default_2d()
+        },
+        Transform::from_translation(CAMERA_POSITION.extend(0.)),
+        bloom_component,
+        tonemapping,
+    ));
+
+    for _ in 0..WARMUP_FRAMES {
+        app.update();
+    }
+    app.world_mut().spawn(Screenshot::image(target)).observe(
+        |trigger: Trigger<ScreenshotCaptured>, mut captured: ResMut<CapturedImage>| {
+            captured.0 = Some(trigger.event().0.clone());
+        },
+    );
+    for _ in 0..CAPTURE_FRAMES {
+        app.update();
+        if let Some(image) = app.world_mut().resource_mut::<CapturedImage>().0.take() {
+            // l'alpha in HDR contiene la luminosità, non la trasparenza
+            return image
+                .try_into_dynamic()
+                .map(|image| image.to_rgb8())
+                .map_err(|err| err.to_string());
+        }
+    }
+    Err(format!("no screenshot after {CAPTURE_FRAMES} frames"))
+}
+
+fn render_target() -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: IMAGE_SIZE.x,
+            height: IMAGE_SIZE.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_DST
+        | TextureUsages::COPY_SRC
+        | TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+/// Numero di pixel con almeno un canale oltre [`CHANNEL_TOLERANCE`].
+pub fn different_pixels(actual: &RgbImage, golden: &RgbImage) -> Result<usize, String> {
+    if actual.dimensions() != golden.dimensions() {
+        return Err(format!(
+            "size {:?} instead of {:?}",
+            actual.dimensions(),
+            golden.dimensions()
+        ));
+    }
+    Ok(actual
+        .pixels()
+        .zip(golden.pixels())
+        .filter(|(a, b)| {
+            a.0.iter()
+                .zip(b.0)
+                .any(|(&a, b)| a.abs_diff(b) > CHANNEL_TOLERANCE)
+        })
+        .count())
+}
+
+/// Renderizza `scene` e la confronta con la sua immagine di riferimento; con
+/// `update` invece la riscrive.
+pub fn check_scene(scene: &GoldenScene, update: bool) -> Result<(), String> {
+    let actual = render_scene(&scene.bloom)?;
+    let golden_path = golden_dir().join(format!("{}.png", scene.name));
+
+    if update {
+        fs::create_dir_all(golden_dir()).map_err(|err| err.to_string())?;
+        return actual.save(&golden_path).map_err(|err| err.to_string());
+    }
+
+    let golden = image::open(&golden_path)
+        .map_err(|err| {
+            format!(
+                "{}: {err} (run with --golden-update to create it)",
+                golden_path.display()
+            )
+        })?
+        .to_rgb8();
+    let different = different_pixels(&actual, &golden)?;
+    let total = (actual.width() * actual.height()) as usize;
+    if different as f32 <= total as f32 * MAX_DIFFERENT_PIXELS {
+        return Ok(());
+    }
+
+    let failed_path = failed_dir().join(format!("{}.png", scene.name));
+    let saved = fs::create_dir_all(failed_dir())
+        .map_err(|err| err.to_string())
+        .and_then(|_| actual.save(&failed_path).map_err(|err| err.to_string()));
+    if let Err(err) = saved {
+        println!("could not save {}: {err}", failed_path.display());
+    }
+    Err(format!(
+        "{different} of {total} pixels differ from {}, render saved to {}",
+        golden_path.display(),
+        failed_path.display()
+    ))
+}
+
+/// Controlla (o con `update` riscrive) tutte le [`scenes`], stampando il risultato
+/// di ognuna.
+pub fn run(update: bool) -> AppExit {
+    let mut failed = false;
+    for scene in scenes() {
+        match check_scene(&scene, update) {
+            Ok(()) if update => println!("{}: updated", scene.name),
+            Ok(()) => println!("{}: ok", scene.name),
+            Err(err) => {
+                println!("{}: FAILED, {err}", scene.name);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        AppExit::error()
+    } else {
+        AppExit::Success
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a Vulkan driver (lavapipe on machines without GPU) and tests/golden"]
+    fn bloom_scenes_match_golden_images() {
+        let failures: Vec<String> = scenes()
+            .iter()
+            .filter_map(|scene| {
+                check_scene(scene, false)
+                    .err()
+                    .map(|err| format!("{}: {err}", scene.name))
+            })
+            .collect();
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+}
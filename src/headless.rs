@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use bevy::{
+    app::ScheduleRunnerPlugin, input::InputPlugin, prelude::*, scene::ScenePlugin,
+    state::app::StatesPlugin, time::TimeUpdateStrategy,
+};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    game_state::{GameRng, GameState, InRun},
+    mechanical_components::joints::JointsPlugin,
+    player_plugin::{LocalPlayers, Player, PlayerPlugin},
+    robot_factory::robot_parts::Head,
+    terrain_plugin::TerrainPlugin,
+    MyTimer, PIXELS_PER_METER,
+};
+
+/// Passo fisso della simulazione: ogni frame dell'app avanza il tempo esattamente di
+/// questo valore, quindi i risultati non dipendono da quanto è veloce la macchina.
+pub const PHYSICS_DT: f32 = 1. / 60.;
+
+/// App senza finestra né rendering, con Rapier a passo fisso e il gioco già in
+/// `GameState::Playing`. Mesh e materiali restano semplici asset in memoria, senza GPU.
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::ZERO)),
+        TransformPlugin,
+        HierarchyPlugin,
+        AssetPlugin::default(),
+        ScenePlugin,
+        StatesPlugin,
+        InputPlugin,
+    ))
+    // il terreno e le creature esistono solo durante una partita
+    .insert_state(GameState::Playing)
+    .add_computed_state::<InRun>()
+    // sempre lo stesso livello, salvo `--seed`
+    .insert_resource(GameRng::new(0))
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(PIXELS_PER_METER))
+    .insert_resource(TimestepMode::Fixed {
+        dt: PHYSICS_DT,
+        substeps: 1,
+    })
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+        PHYSICS_DT,
+    )));
+    app
+}
+
+/// Passi di simulazione ancora da eseguire prima di uscire.
+#[derive(Resource)]
+struct RemainingSteps(u32);
+
+/// Posizione iniziale della testa di ogni giocatore, per il resoconto finale.
+#[derive(Resource, Default)]
+struct StartPositions(Vec<(Entity, Vec2)>);
+
+/// [`headless_app`] con le creature di `local_players` ma senza terreno.
+pub fn creatures_app(local_players: LocalPlayers) -> App {
+    let mut app = headless_app();
+    app.insert_resource(local_players)
+        .insert_resource(MyTimer(Timer::from_seconds(
+            std::f32::consts::TAU,
+            TimerMode::Repeating,
+        )))
+        .add_plugins((PlayerPlugin, JointsPlugin));
+    app
+}
+
+/// [`headless_app`] con il terreno e le creature di `local_players`, come nel gioco.
+pub fn gameplay_app(local_players: LocalPlayers) -> App {
+    let mut app = creatures_app(local_players);
+    app.add_plugins(TerrainPlugin);
+    app
+}
+
+/// Simula `steps` passi di fisica con le creature dei giocatori e il terreno, poi
+/// stampa quanto si è spostata ogni testa ed esce. `configure` aggiunge all'app
+/// quello che la riga di comando chiede (seed, livello, log delle collisioni...).
+pub fn run(steps: u32, local_players: LocalPlayers, configure: impl FnOnce(&mut App)) -> AppExit {
+    let mut app = gameplay_app(local_players);
+    app.insert_resource(RemainingSteps(steps))
+        .init_resource::<StartPositions>()
+        .add_systems(Last, count_steps);
+    configure(&mut app);
+    app.run()
+}
+
+fn count_steps(
+    mut remaining: ResMut<RemainingSteps>,
+    mut start: ResMut<StartPositions>,
+    players: Query<(Entity, &Head), With<Player>>,
+    heads: Query<&GlobalTransform>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let positions: Vec<(Entity, Vec2)> = players
+        .iter()
+        .filter_map(|(player, head)| {
+            let head = heads.get(head.0).ok()?;
+            Some((player, head.translation().truncate()))
+        })
+        .collect();
+    if start.0.is_empty() {
+        start.0 = positions.clone();
+    }
+
+    remaining.0 = remaining.0.saturating_sub(1);
+    if remaining.0 > 0 {
+        return;
+    }
+    for (player, end) in positions {
+        let Some((_, begin)) = start.0.iter().find(|(entity, _)| *entity == player) else {
+            continue;
+        };
+        println!(
+            "player {player}: head from {begin} to {end}, moved {:.1}",
+            begin.distance(end)
+        );
+    }
+    exit.send(AppExit::Success);
+}
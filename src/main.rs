@@ -1,96 +1,148 @@
 #![allow(unused)]
 
+mod bench;
 mod camera_plugin;
+mod cli;
+mod collision_log;
+mod config;
+#[cfg(feature = "debug")]
+mod debug;
+mod evolution;
+mod game_state;
+mod golden;
+mod headless;
 mod mechanical_components;
 mod robot_factory;
 mod player_plugin;
+mod replay;
+mod settings;
+mod snapshot;
 mod terrain_plugin;
 mod bevy_rapier2d_example;
 
 use std::f32::consts::PI;
 
 use bevy::{color::palettes::{css::{BLACK, BLUE_VIOLET}, tailwind::BLUE_100}, diagnostic::{EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin, SystemInformationDiagnosticsPlugin}, prelude::*};
+use bevy::audio::AudioPlugin;
 use bevy_rapier2d::prelude::*;
 use bevy_rapier2d_example::BevyRapierExamplePlugin;
 use camera_plugin::CameraPlugin;
-use iyes_perf_ui::{
-    entries::{PerfUiFramerateEntries, PerfUiSystemEntries, PerfUiWindowEntries},
-    prelude::{PerfUiAllEntries, PerfUiEntryFPS, PerfUiEntryFPSWorst, PerfUiRoot},
-    PerfUiPlugin,
-};
+use clap::Parser;
+use cli::Cli;
+use game_state::GameStatePlugin;
+use mechanical_components::joints::JointsPlugin;
 use bevy::{
     dev_tools::fps_overlay::{FpsOverlayConfig, FpsOverlayPlugin},
     text::FontSmoothing,
 };
-use player_plugin::PlayerPlugin;
+use config::load_config;
+use player_plugin::{LocalPlayers, PlayerPlugin};
+use settings::{GameSettings, SettingsPlugin, SETTINGS_FILE};
+use snapshot::SnapshotPlugin;
 use terrain_plugin::TerrainPlugin;
 
 #[derive(Resource)]
 struct MyTimer(Timer);
+/// Scala tra il mondo di Bevy e quello di Rapier: le posizioni restano in pixel,
+/// Rapier la usa per le tolleranze e per convertire forze e impulsi in unità SI.
+pub const PIXELS_PER_METER: f32 = 100.;
 fn main() {
-    App::new()
+    let cli = Cli::parse();
+
+    if cli.evolve {
+        let defaults = evolution::EvolutionConfig::default();
+        let exit = evolution::run(evolution::EvolutionConfig {
+            seed: cli.seed.unwrap_or(defaults.seed),
+            ..defaults
+        });
+        if exit.is_error() {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if cli.golden || cli.golden_update {
+        if golden::run(cli.golden_update).is_error() {
+            std::process::exit(1);
+        }
+        return;
+    }
+    // meglio con `--release`
+    if let Some(output) = cli.bench.clone() {
+        bench::run(output);
+        return;
+    }
+
+    let mut settings: GameSettings = load_config(SETTINGS_FILE);
+    cli.apply_window(&mut settings);
+
+    let scenario = match cli.scenario() {
+        Ok(scenario) => scenario,
+        Err(err) => {
+            println!("{err}");
+            return;
+        }
+    };
+    let local_players = LocalPlayers::new(scenario.players(cli.players), &settings.bindings);
+
+    if let Some(steps) = cli.headless {
+        headless::run(steps, local_players, |app| scenario.configure(app));
+        return;
+    }
+
+    let mut app = App::new();
+    app.insert_resource(local_players)
         .insert_resource(MyTimer(Timer::from_seconds(2.*PI, TimerMode::Repeating)))
         .insert_resource(ClearColor(BLACK.into()))
-        .add_plugins(DefaultPlugins)
-        .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-        .add_plugins((CameraPlugin, PlayerPlugin, TerrainPlugin))
-        
-        // DEBUG
-        //.add_plugins(RapierDebugRenderPlugin::default())
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(settings.window()),
+                    ..default()
+                })
+                .set(AudioPlugin {
+                    global_volume: GlobalVolume::new(settings.master_volume),
+                    ..default()
+                }),
+        )
+        .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(PIXELS_PER_METER))
+        .add_plugins((GameStatePlugin, CameraPlugin, PlayerPlugin, TerrainPlugin, JointsPlugin))
         //.add_plugins(BevyRapierExamplePlugin)
-        //.add_plugins((
-        //    FrameTimeDiagnosticsPlugin,
-        //    SystemInformationDiagnosticsPlugin,
-        //    EntityCountDiagnosticsPlugin,
-        //    PerfUiPlugin,
-        //))
-        //.add_systems(Update, toggle.before(iyes_perf_ui::PerfUiSet::Setup))
         .add_plugins(FpsOverlayPlugin {
             config: FpsOverlayConfig {
                 text_config: TextFont {
-                    font_size: 42.0,
+                    font_size: settings.fps_font_size,
                     font: default(),
                     font_smoothing: FontSmoothing::default(),
                 },
                 text_color: Color::srgb(0.0, 1.0, 0.0),
-                enabled: true,
+                enabled: settings.show_fps,
             },
         })
+        .insert_resource(settings)
+        .add_plugins((SettingsPlugin, SnapshotPlugin));
         // startup
         //.add_systems(Startup, setup_instructions)
 
-        .run();
-}
-fn toggle(
-    mut commands: Commands,
-    q_root: Query<Entity, With<PerfUiRoot>>,
-    kbd: Res<ButtonInput<KeyCode>>,
-) {
-    if kbd.just_pressed(KeyCode::F12) {
-        if let Ok(e) = q_root.get_single() {
-            // despawn the existing Perf UI
-            commands.entity(e).despawn_recursive();
-        } else {
-            // create a simple Perf UI with default settings
-            // and all entries provided by the crate:
-            commands.spawn(PerfUiAllEntries::default());
-        }
+    scenario.configure(&mut app);
+
+    // `cargo run --features debug`: collider, joint, perf UI e inspector (vedi `debug`)
+    #[cfg(feature = "debug")]
+    app.add_plugins(debug::DebugPlugin);
+    if cli.debug {
+        #[cfg(feature = "debug")]
+        app.insert_resource(debug::DebugOverlays {
+            colliders: true,
+            joints: true,
+            perf_ui: true,
+            ..default()
+        });
+        #[cfg(not(feature = "debug"))]
+        println!("--debug needs a build with `--features debug`");
     }
+
+    app.run();
 }
 
 fn setup_instructions(mut commands: Commands) {
     commands.spawn(Text::new("WASD per muoversi"));
 }
-
-fn display_events(
-    mut collision_events: EventReader<CollisionEvent>,
-    mut contact_force_events: EventReader<ContactForceEvent>,
-) {
-    for collision_event in collision_events.read() {
-        println!("Received collision event: {:?}", collision_event);
-    }
-
-    for contact_force_event in contact_force_events.read() {
-        println!("Received contact force event: {:?}", contact_force_event);
-    }
-}
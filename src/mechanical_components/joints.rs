@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use bevy_rapier2d::{prelude::*, rapier::dynamics::JointAxis};
+use serde::{Deserialize, Serialize};
+
+/// Soglie oltre le quali l'`ImpulseJoint` dell'entità viene rimosso.
+///
+/// Le forze sono ricavate dagli impulsi che Rapier applica al joint durante lo step e
+/// convertite in unità SI con `length_unit` (il `pixels_per_meter` del plugin): la
+/// simulazione lavora in pixel, quindi una forza in pixel va divisa per `length_unit`
+/// e una coppia per `length_unit^2`. Con le masse in kg sono newton e newton metro.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize, Reflect)]
+#[reflect(Component)]
+pub struct Breakable {
+    pub max_force: f32,
+    pub max_torque: f32,
+}
+
+/// Lunghezza massima del `RopeJoint` dell'entità. Cambiandola (es. dall'inspector)
+/// la corda in Rapier viene aggiornata subito.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct RopeLength(pub f32);
+
+/// Inviato quando un joint `Breakable` supera una delle sue soglie.
+#[derive(Event, Debug)]
+pub struct JointBrokenEvent {
+    /// Entità che portava l'`ImpulseJoint`, ora libera.
+    pub entity: Entity,
+    /// Corpo a cui era agganciata.
+    pub parent: Entity,
+    pub force: f32,
+    pub torque: f32,
+}
+
+pub struct JointsPlugin;
+impl Plugin for JointsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<JointBrokenEvent>()
+            .register_type::<Breakable>()
+            .register_type::<RopeLength>()
+            .add_systems(
+                PostUpdate,
+                (
+                    apply_rope_lengths.before(PhysicsSet::SyncBackend),
+                    break_overloaded_joints.after(PhysicsSet::Writeback),
+                ),
+            );
+    }
+}
+
+fn apply_rope_lengths(mut ropes: Query<(&RopeLength, &mut ImpulseJoint), Changed<RopeLength>>) {
+    for (length, mut joint) in &mut ropes {
+        if let TypedJoint::RopeJoint(rope) = &mut joint.data {
+            rope.set_max_distance(length.0);
+        }
+    }
+}
+
+/// Rimuove i joint sovraccaricati. La velocità del corpo liberato non viene toccata,
+/// quindi il pezzo staccato prosegue con la quantità di moto che aveva.
+fn break_overloaded_joints(
+    mut commands: Commands,
+    rapier_context: ReadDefaultRapierContext,
+    joints: Query<(Entity, &ImpulseJoint, &RapierImpulseJointHandle, &Breakable)>,
+    mut broken_joints: EventWriter<JointBrokenEvent>,
+) {
+    let context = rapier_context.single();
+    let dt = context.integration_parameters.dt;
+    let length_unit = context.integration_parameters.length_unit;
+    if dt <= 0. {
+        return;
+    }
+
+    for (entity, joint, handle, breakable) in &joints {
+        let Some(rapier_joint) = context.impulse_joints.get(handle.0) else {
+            continue;
+        };
+        // gli assi bloccati (es. revolute) scrivono in `impulses`, i limiti (es. la
+        // distanza massima di una corda) nel loro `JointLimits`
+        let impulses = rapier_joint.impulses;
+        let limits = &rapier_joint.data.limits;
+        let linear = Vec2::new(
+            impulses.x + limits[JointAxis::LinX as usize].impulse,
+            impulses.y + limits[JointAxis::LinY as usize].impulse,
+        );
+        let angular = impulses.z + limits[JointAxis::AngX as usize].impulse;
+        let force = linear.length() / dt / length_unit;
+        let torque = angular.abs() / dt / (length_unit * length_unit);
+
+        if force > breakable.max_force || torque > breakable.max_torque {
+            commands
+                .entity(entity)
+                .remove::<(ImpulseJoint, Breakable, RopeLength)>();
+            broken_joints.send(JointBrokenEvent {
+                entity,
+                parent: joint.parent,
+                force,
+                torque,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::headless_app;
+
+    /// Segmento agganciato con una corda a un corpo più pesante: volano insieme verso
+    /// destra, il segmento sbatte contro un cubo fisso mentre l'altro gli passa sopra.
+    #[test]
+    fn segment_slammed_into_terrain_breaks_its_rope() {
+        let mut app = headless_app();
+        app.add_plugins(JointsPlugin);
+        app.finish();
+        app.cleanup();
+
+        let speed = Vec2::new(5_000., 0.);
+        let world = app.world_mut();
+        world.spawn((
+            RigidBody::Fixed,
+            Collider::cuboid(50., 50.),
+            Transform::from_xyz(300., 0., 0.),
+        ));
+        let parent = world
+            .spawn((
+                RigidBody::Dynamic,
+                Collider::ball(10.),
+                ColliderMassProperties::Mass(10.),
+                Velocity::linear(speed),
+                Transform::from_xyz(150., 150., 0.),
+            ))
+            .id();
+        let rope = RopeJointBuilder::new(Vec2::splat(150.).length());
+        let segment = world
+            .spawn((
+                RigidBody::Dynamic,
+                Collider::ball(10.),
+                ColliderMassProperties::Mass(1.),
+                Velocity::linear(speed),
+                Ccd::enabled(),
+                Transform::from_xyz(0., 0., 0.),
+                ImpulseJoint::new(parent, rope),
+                Breakable {
+                    max_force: 5_000.,
+                    max_torque: f32::INFINITY,
+                },
+            ))
+            .id();
+
+        let mut broken = vec![];
+        for _ in 0..30 {
+            app.update();
+            broken.extend(
+                app.world_mut()
+                    .resource_mut::<Events<JointBrokenEvent>>()
+                    .drain(),
+            );
+        }
+
+        assert!(
+            broken
+                .iter()
+                .any(|event| event.entity == segment && event.parent == parent),
+            "the rope held: {broken:?}"
+        );
+        assert!(app.world().get::<ImpulseJoint>(segment).is_none());
+    }
+}
@@ -1 +1,2 @@
-pub mod generic;
\ No newline at end of file
+pub mod generic;
+pub mod joints;
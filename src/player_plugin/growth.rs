@@ -0,0 +1,161 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    mechanical_components::{
+        generic::{GenericMechanicalComponentBundle, MyRigidBody, Shape},
+        joints::{Breakable, RopeLength},
+    },
+    robot_factory::{
+        joint_chain,
+        robot_parts::{Head, PartColor, PartOf, RobotBody},
+    },
+};
+
+use super::{
+    segment_color, segment_radius, segment_rope_distance, Player, PlayerPalette,
+    GAP_BETWEEN_BALLS, HEAD_MASS, SEGMENT_BREAK_FORCE,
+};
+
+/// Aggiunge un segmento in coda al corpo del player.
+#[derive(Event)]
+pub struct GrowCreature {
+    pub player: Entity,
+}
+
+/// Rimuove l'ultimo segmento della coda del player.
+#[derive(Event)]
+pub struct ShrinkCreature {
+    pub player: Entity,
+}
+
+/// Applica gli eventi di crescita/riduzione: i segmenti rimasti vengono riprofilati
+/// (raggio, colore e lunghezza delle corde) come se il corpo fosse stato spawnato
+/// direttamente con la nuova lunghezza.
+pub(super) fn resize_creatures(
+    mut commands: Commands,
+    mut grow_events: EventReader<GrowCreature>,
+    mut shrink_events: EventReader<ShrinkCreature>,
+    players: Query<(&Head, &PlayerPalette), With<Player>>,
+    bodies: Query<(&Transform, &Velocity)>,
+    mut segments: Query<
+        (
+            &mut Collider,
+            &mut Mesh2d,
+            &MeshMaterial2d<ColorMaterial>,
+            Option<&mut PartColor>,
+        ),
+        With<RobotBody>,
+    >,
+    joints: Query<(Entity, &ImpulseJoint)>,
+    mut rope_lengths: Query<&mut RopeLength>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    // più eventi nello stesso frame vengono sommati, la catena viene letta una volta sola
+    let mut deltas: HashMap<Entity, i32> = HashMap::default();
+    for event in grow_events.read() {
+        *deltas.entry(event.player).or_default() += 1;
+    }
+    for event in shrink_events.read() {
+        *deltas.entry(event.player).or_default() -= 1;
+    }
+    if deltas.is_empty() {
+        return;
+    }
+
+    let links: HashMap<Entity, Entity> = joints
+        .iter()
+        .map(|(entity, joint)| (joint.parent, entity))
+        .collect();
+
+    for (player, delta) in deltas {
+        let Ok((&Head(head), palette)) = players.get(player) else {
+            continue;
+        };
+        let mut chain: Vec<Entity> = joint_chain(head, &links)
+            .into_iter()
+            .take_while(|part| segments.contains(*part))
+            .collect();
+        let new_len = (chain.len() as i32 + delta).max(1) as usize;
+
+        while chain.len() > new_len {
+            let tail = chain.pop().unwrap();
+            commands.entity(tail).despawn_recursive();
+        }
+
+        for (k, &part) in chain.iter().enumerate() {
+            let radius = segment_radius(k, new_len);
+            let (mut collider, mut mesh, material, part_color) = segments.get_mut(part).unwrap();
+            *collider = Collider::ball(radius);
+            *mesh = Mesh2d(meshes.add(Circle::new(radius)));
+            let color = segment_color(k, new_len, palette.body);
+            if let Some(material) = materials.get_mut(&material.0) {
+                material.color = color;
+            }
+            if let Some(mut part_color) = part_color {
+                part_color.0 = color;
+            }
+            // la corda tra testa e primo segmento è quella del Robot, non si tocca
+            if k > 0 {
+                if let Ok(mut length) = rope_lengths.get_mut(part) {
+                    length.0 = segment_rope_distance(segment_radius(k - 1, new_len), radius);
+                }
+            }
+        }
+
+        // il primo segmento non è Breakable, quindi la catena non è mai vuota
+        let Some(&tail) = chain.last() else {
+            continue;
+        };
+        if chain.len() == new_len {
+            continue;
+        }
+        // i nuovi segmenti proseguono la direzione della coda, alla distanza di riposo
+        // e con la sua stessa velocità, così la catena non riceve strattoni
+        let before_tail = chain.iter().rev().nth(1).copied().unwrap_or(head);
+        let (tail_transform, &velocity) = bodies.get(tail).unwrap();
+        let mut parent = tail;
+        let mut parent_pos = tail_transform.translation.truncate();
+        let before_pos = bodies.get(before_tail).unwrap().0.translation.truncate();
+        let direction = (parent_pos - before_pos).normalize_or(Vec2::X);
+        let mut parent_radius = segment_radius(chain.len() - 1, new_len);
+
+        for k in chain.len()..new_len {
+            let radius = segment_radius(k, new_len);
+            let position = parent_pos + direction * (parent_radius + radius + GAP_BETWEEN_BALLS);
+            let mut bundle = GenericMechanicalComponentBundle::new(
+                MyRigidBody::Dynamic {
+                    mass: HEAD_MASS * 0.1,
+                },
+                Shape::Ball { radius },
+                segment_color(k, new_len, palette.body),
+                Transform::from_translation(position.extend(0.)),
+                &mut meshes,
+                &mut materials,
+            );
+            bundle.velocity = velocity;
+            let joint = RopeJointBuilder::new(segment_rope_distance(parent_radius, radius))
+                .local_anchor1(Vec2 { x: 0., y: 0. })
+                .local_anchor2(Vec2 { x: 0., y: 0. });
+            let segment = commands
+                .spawn((
+                    RobotBody,
+                    PartOf(player),
+                    bundle,
+                    ImpulseJoint::new(parent, joint),
+                    RopeLength(segment_rope_distance(parent_radius, radius)),
+                    Breakable {
+                        max_force: SEGMENT_BREAK_FORCE,
+                        max_torque: f32::INFINITY,
+                    },
+                ))
+                .id();
+            commands.entity(player).add_child(segment);
+
+            parent = segment;
+            parent_pos = position;
+            parent_radius = radius;
+        }
+    }
+}
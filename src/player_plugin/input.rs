@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Dispositivo con cui un giocatore locale controlla la sua creatura.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputBinding {
+    Keyboard(KeyboardScheme),
+    /// N-esimo gamepad connesso.
+    Gamepad(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyboardScheme {
+    Wasd,
+    Arrows,
+    Numpad,
+}
+
+struct KeyMap {
+    up: KeyCode,
+    down: KeyCode,
+    left: KeyCode,
+    right: KeyCode,
+    oscillate: KeyCode,
+}
+
+/// Azioni del giocatore in questo frame, indipendenti dal dispositivo.
+#[derive(Component, Clone, Copy, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerInput {
+    pub movement: Vec2,
+    pub toggle_oscillation: bool,
+}
+
+impl InputBinding {
+    /// Binding di default per lo slot: i primi due giocatori usano la tastiera,
+    /// gli altri i gamepad nell'ordine in cui sono stati collegati.
+    pub fn for_slot(slot: usize) -> Self {
+        match slot {
+            0 => InputBinding::Keyboard(KeyboardScheme::Wasd),
+            1 => InputBinding::Keyboard(KeyboardScheme::Arrows),
+            n => InputBinding::Gamepad(n - 2),
+        }
+    }
+}
+
+impl KeyboardScheme {
+    fn keys(self) -> KeyMap {
+        match self {
+            KeyboardScheme::Wasd => KeyMap {
+                up: KeyCode::KeyW,
+                down: KeyCode::KeyS,
+                left: KeyCode::KeyA,
+                right: KeyCode::KeyD,
+                oscillate: KeyCode::KeyK,
+            },
+            KeyboardScheme::Arrows => KeyMap {
+                up: KeyCode::ArrowUp,
+                down: KeyCode::ArrowDown,
+                left: KeyCode::ArrowLeft,
+                right: KeyCode::ArrowRight,
+                oscillate: KeyCode::ShiftRight,
+            },
+            KeyboardScheme::Numpad => KeyMap {
+                up: KeyCode::Numpad8,
+                down: KeyCode::Numpad5,
+                left: KeyCode::Numpad4,
+                right: KeyCode::Numpad6,
+                oscillate: KeyCode::Numpad0,
+            },
+        }
+    }
+}
+
+pub(super) fn read_player_input(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut players: Query<(&InputBinding, &mut PlayerInput)>,
+) {
+    for (binding, mut input) in &mut players {
+        *input = match *binding {
+            InputBinding::Keyboard(scheme) => {
+                let keys = scheme.keys();
+                let mut movement = Vec2::ZERO;
+                if kb_input.pressed(keys.up) {
+                    movement += Vec2 { x: 0.0, y: 1. };
+                }
+                if kb_input.pressed(keys.down) {
+                    movement += Vec2 { x: 0.0, y: -1. };
+                }
+                if kb_input.pressed(keys.left) {
+                    movement += Vec2 { x: -1.0, y: 0. };
+                }
+                if kb_input.pressed(keys.right) {
+                    movement += Vec2 { x: 1.0, y: 0. };
+                }
+                PlayerInput {
+                    movement,
+                    toggle_oscillation: kb_input.just_pressed(keys.oscillate),
+                }
+            }
+            InputBinding::Gamepad(index) => match gamepads.iter().nth(index) {
+                Some(gamepad) => PlayerInput {
+                    movement: gamepad.left_stick(),
+                    toggle_oscillation: gamepad.just_pressed(GamepadButton::South),
+                },
+                None => PlayerInput::default(),
+            },
+        };
+    }
+}
@@ -1,4 +1,8 @@
+mod growth;
+mod input;
 pub mod player_assembly;
+#[cfg(test)]
+mod tests;
 
 use bevy::{
     color::palettes::tailwind::{BLUE_100, BLUE_950, RED_100},
@@ -7,29 +11,83 @@ use bevy::{
     prelude::*,
     text::cosmic_text::ttf_parser::head,
     transform,
-    utils::hashbrown::HashMap,
+    utils::HashMap,
 };
 use bevy_rapier2d::prelude::*;
 
 use crate::{
-    mechanical_components::generic::{
-        GenericMechanicalComponentBundle, MyPosition, MyRigidBody, Shape,
+    camera_plugin::PhotoMode,
+    mechanical_components::{
+        generic::{GenericMechanicalComponentBundle, MyPosition, MyRigidBody, Shape},
+        joints::{Breakable, JointBrokenEvent, RopeLength},
     },
+    game_state::{GameState, InRun},
     robot_factory::{
-        robot_parts::{Robot, RobotBody, RobotHead},
+        joint_chain,
+        robot_parts::{Head, PartColor, PartOf, Robot, RobotBody, RobotHead},
         spawn_robot,
     },
-    MyTimer,
+    MyTimer, PIXELS_PER_METER,
 };
+pub use growth::{GrowCreature, ShrinkCreature};
+use growth::resize_creatures;
+pub use input::{InputBinding, KeyboardScheme, PlayerInput};
+use input::read_player_input;
 
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_player)
-            .add_systems(Update, move_player);
+        app.add_event::<GrowCreature>()
+            .add_event::<ShrinkCreature>()
+            .init_resource::<LocalPlayers>()
+            .init_resource::<ChainLength>()
+            .register_type::<TriggerOscillation>()
+            .register_type::<PlayerSlot>()
+            .register_type::<PlayerPalette>()
+            .register_type::<Robot>()
+            .register_type::<RobotHead>()
+            .register_type::<RobotBody>()
+            .register_type::<Head>()
+            .register_type::<PartOf>()
+            .register_type::<PartColor>()
+            .add_systems(OnEnter(InRun), spawn_player)
+            .add_systems(
+                Update,
+                (
+                    (
+                        read_player_input.in_set(PlayerSet::ReadInput),
+                        move_player.in_set(PlayerSet::Move),
+                    )
+                        .run_if(not_in_photo_mode),
+                    drop_broken_tail,
+                    resize_creatures,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (add_part_colors, apply_part_colors, apply_robot_rope_lengths),
+            )
+            .configure_sets(Update, PlayerSet::ReadInput.before(PlayerSet::Move));
     }
 }
 
+/// In modalità foto i tasti muovono la camera libera: le creature non li ricevono e
+/// non accumulano impulsi mentre la fisica è ferma.
+fn not_in_photo_mode(photo_mode: Option<Res<PhotoMode>>) -> bool {
+    !photo_mode.is_some_and(|photo_mode| photo_mode.active)
+}
+
+/// Fasi del controllo dei giocatori: chi vuole cambiare gli input (es. un replay)
+/// si mette tra le due.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PlayerSet {
+    /// Riempie `PlayerInput` da tastiera e gamepad.
+    ReadInput,
+    /// Applica `PlayerInput` alle creature.
+    Move,
+}
+
 #[derive(Event)]
 struct AttachJointEvent {
     player: Entity,
@@ -39,10 +97,139 @@ struct AttachJointEvent {
 #[derive(Component, QueryData)]
 pub struct Player;
 
+/// Indice del giocatore locale (0..4): decide binding, colori e camera.
+#[derive(Component, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct PlayerSlot(pub usize);
+
+/// Giocatori locali da spawnare, uno per binding.
+#[derive(Resource)]
+pub struct LocalPlayers {
+    pub bindings: Vec<InputBinding>,
+}
+
+impl Default for LocalPlayers {
+    fn default() -> Self {
+        Self::new(1, &[])
+    }
+}
+
+impl LocalPlayers {
+    pub const MAX: usize = 4;
+
+    /// `bindings` sono quelli scelti nelle impostazioni; gli slot mancanti usano
+    /// [`InputBinding::for_slot`].
+    pub fn new(count: usize, bindings: &[InputBinding]) -> Self {
+        Self {
+            bindings: (0..count.clamp(1, Self::MAX))
+                .map(|slot| {
+                    bindings
+                        .get(slot)
+                        .copied()
+                        .unwrap_or_else(|| InputBinding::for_slot(slot))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Colori della creatura: la testa e la tinta del corpo, scalata dall'intensità
+/// di ogni segmento.
+#[derive(Component, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct PlayerPalette {
+    pub head: Color,
+    pub body: Vec3,
+}
+
+const PALETTES: [PlayerPalette; LocalPlayers::MAX] = [
+    PlayerPalette {
+        head: Color::linear_rgb(3., 0., 16.),
+        body: Vec3::new(0., 0., 1.),
+    },
+    PlayerPalette {
+        head: Color::linear_rgb(16., 2., 0.),
+        body: Vec3::new(1., 0.1, 0.),
+    },
+    PlayerPalette {
+        head: Color::linear_rgb(0., 16., 3.),
+        body: Vec3::new(0., 1., 0.2),
+    },
+    PlayerPalette {
+        head: Color::linear_rgb(12., 0., 12.),
+        body: Vec3::new(1., 0., 1.),
+    },
+];
+impl PlayerPalette {
+    pub fn for_slot(slot: usize) -> Self {
+        PALETTES[slot % PALETTES.len()]
+    }
+}
+
+/// Segmenti del corpo di ogni creatura allo spawn, escluso il primo.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ChainLength(pub usize);
+
+impl Default for ChainLength {
+    fn default() -> Self {
+        Self(BALL_NUMS)
+    }
+}
+
+/// Distanza verticale tra i punti di spawn dei giocatori, verso l'alto: sotto c'è il terreno.
+const PLAYER_SPACING: f32 = 700.;
+
 fn spawn_player(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    local_players: Res<LocalPlayers>,
+    chain_length: Res<ChainLength>,
+) {
+    for (slot, &binding) in local_players.bindings.iter().enumerate() {
+        spawn_player_creature(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            slot,
+            binding,
+            chain_length.0,
+        );
+    }
+}
+
+/// Entità principale del giocatore, senza pezzi: `Head` e i figli vanno aggiunti dopo.
+pub(crate) fn spawn_player_root(
+    commands: &mut Commands,
+    slot: usize,
+    binding: InputBinding,
+    transform: Transform,
+    robot: Robot,
+    oscillation: TriggerOscillation,
+) -> Entity {
+    commands
+        .spawn((
+            Player,
+            PlayerSlot(slot),
+            binding,
+            PlayerInput::default(),
+            PlayerPalette::for_slot(slot),
+            oscillation,
+            transform,
+            robot,
+            Visibility::default(),
+            StateScoped(InRun),
+        ))
+        .id()
+}
+
+fn spawn_player_creature(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    slot: usize,
+    binding: InputBinding,
+    ball_nums: usize,
 ) {
     // to keep track for measurments
     let mut robot_parts = vec![];
@@ -50,42 +237,41 @@ fn spawn_player(
     let mut positions = vec![];
     // player config
     let mut rope_distance = 250.;
-    let player_pos = MyPosition { x: 0., y: 0. };
+    let player_pos = MyPosition {
+        x: 0.,
+        y: slot as f32 * PLAYER_SPACING,
+    };
+    let palette = PlayerPalette::for_slot(slot);
 
     // robot config
     let robot = Robot {
         rope_lenght: rope_distance,
     };
-    let gap_between_balls = 9.;
+    let gap_between_balls = GAP_BETWEEN_BALLS;
 
     // head config
-    let head_mass = 1.5;
-    let head_color = Color::linear_rgb(3., 0., 16.);
-    let head_radius = 100.;
+    let head_mass = HEAD_MASS;
+    let head_color = palette.head;
+    let head_radius = HEAD_RADIUS;
     let head_pos = Transform::from_xyz(0.0, 0.0, 0.0);
     positions.push(head_pos);
     let loc_anchor1 = Vec2 { x: 0., y: 0. };
     let loc_anchor2 = Vec2 { x: 0., y: 0. };
 
     // body part 1 config
-    let body_part1_radius = head_radius * 0.9;
+    let body_part1_radius = segment_radius(0, ball_nums + 1);
     ball_radiuses.push(body_part1_radius);
     let body_part1_x = head_radius + body_part1_radius + gap_between_balls;
     positions.push(Transform::from_xyz(body_part1_x, 0., 0.));
 
-    // other body part config
-    let ball_nums = 48 * 3;
-    let color_intensity = 10.;
-
-    let player = commands
-        .spawn((
-            Player,
-            TriggerOscillation(false),
-            player_pos.to_transform(),
-            robot,
-            Visibility::default(),
-        ))
-        .id();
+    let player = spawn_player_root(
+        commands,
+        slot,
+        binding,
+        player_pos.to_transform(),
+        robot,
+        TriggerOscillation(false),
+    );
 
     // Head spawn
     let head = commands
@@ -98,14 +284,15 @@ fn spawn_player(
                 },
                 head_color,
                 head_pos,
-                &mut meshes,
-                &mut materials,
+                meshes,
+                materials,
             ),
         ))
         .id();
     robot_parts.push(head);
     // build the joint
-    let head_joint = RopeJointBuilder::new(40.)
+    let head_rope_length = 40.;
+    let head_joint = RopeJointBuilder::new(head_rope_length)
         .local_anchor1(loc_anchor1)
         .local_anchor2(loc_anchor2);
     let body_part1 = commands
@@ -118,10 +305,10 @@ fn spawn_player(
                 Shape::Ball {
                     radius: body_part1_radius,
                 },
-                Color::linear_rgb(0., 0., 10.),
+                segment_color(0, ball_nums + 1, palette.body),
                 Transform::from_xyz(body_part1_x, 0., 0.),
-                &mut meshes,
-                &mut materials,
+                meshes,
+                materials,
             ),
         ))
         .id();
@@ -132,10 +319,12 @@ fn spawn_player(
     //    rope_joint.set_contacts_enabled(false);
     //}
     // attach the joint
-    commands.entity(body_part1).insert(impulse_joint);
+    commands
+        .entity(body_part1)
+        .insert((impulse_joint, RopeLength(head_rope_length)));
 
     for i in 1..=ball_nums {
-        let radius = head_radius * ((ball_nums - i + 1) as f32) / ball_nums as f32;
+        let radius = segment_radius(i, ball_nums + 1);
         let last_ball_radius = ball_radiuses.last().unwrap().to_owned();
         ball_radiuses.push(radius);
         let last_x_pos = positions.last().unwrap().translation.x;
@@ -149,10 +338,10 @@ fn spawn_player(
                         mass: head_mass * 0.1,
                     },
                     Shape::Ball { radius },
-                    Color::linear_rgb(0., 0., (ball_nums - i) as f32 * color_intensity),
+                    segment_color(i, ball_nums + 1, palette.body),
                     Transform::from_xyz(x_pos, 0., 0.),
-                    &mut meshes,
-                    &mut materials,
+                    meshes,
+                    materials,
                 ),
             ))
             .id();
@@ -162,7 +351,7 @@ fn spawn_player(
         let part1 = pairs_of_parts[0];
         let part2 = pairs_of_parts[1];
         if n > 0 {
-            rope_distance = ball_radiuses[n - 1] + ball_radiuses[n] + gap_between_balls + 3.;
+            rope_distance = segment_rope_distance(ball_radiuses[n - 1], ball_radiuses[n]);
         }
         let joint = RopeJointBuilder::new(rope_distance)
             .local_anchor1(Vec2 { x: 0., y: 0. })
@@ -171,71 +360,174 @@ fn spawn_player(
         //if let TypedJoint::RopeJoint(mut rope_joint) = impulse_joint.data {
         //    rope_joint.set_contacts_enabled(false);
         //}
-        commands.entity(part2).insert(impulse_joint);
+        commands
+            .entity(part2)
+            .insert((impulse_joint, RopeLength(rope_distance)));
+        // la testa non si stacca mai, la coda sì
+        if n > 0 {
+            commands.entity(part2).insert(Breakable {
+                max_force: SEGMENT_BREAK_FORCE,
+                max_torque: f32::INFINITY,
+            });
+        }
     }
 
     // add child to player
-    commands.entity(player).add_children(&robot_parts);
+    commands
+        .entity(player)
+        .add_children(&robot_parts)
+        .insert(Head(head));
+    for &part in &robot_parts {
+        commands.entity(part).insert(PartOf(player));
+    }
     //dbg!(positions);
 }
+
+const HEAD_RADIUS: f32 = 100.;
+const HEAD_MASS: f32 = 1.5;
+const GAP_BETWEEN_BALLS: f32 = 9.;
+const COLOR_INTENSITY: f32 = 10.;
+/// Segmenti del corpo allo spawn, escluso il primo (quello subito dietro la testa).
+const BALL_NUMS: usize = 48 * 3;
+
+/// Raggio del segmento `k` (0 = subito dietro la testa) per un corpo di `segments` segmenti:
+/// la coda si assottiglia linearmente fino all'ultimo segmento.
+fn segment_radius(k: usize, segments: usize) -> f32 {
+    if k == 0 {
+        return HEAD_RADIUS * 0.9;
+    }
+    HEAD_RADIUS * (segments - k) as f32 / (segments - 1) as f32
+}
+
+fn segment_color(k: usize, segments: usize, tint: Vec3) -> Color {
+    let intensity = if k == 0 {
+        10.
+    } else {
+        (segments - 1 - k) as f32 * COLOR_INTENSITY
+    };
+    let Vec3 { x, y, z } = tint * intensity;
+    Color::linear_rgb(x, y, z)
+}
+
+/// Lunghezza della corda tra due segmenti consecutivi di raggio `r1` e `r2`.
+fn segment_rope_distance(r1: f32, r2: f32) -> f32 {
+    r1 + r2 + GAP_BETWEEN_BALLS + 3.
+}
 const PLAYER_LENGTH: f32 = 50.; // meters
 const PLAYER_ACCELERATION_FORCE: f32 = 50. * 9.; // newton
+/// Spinta della testa con l'input al massimo, in unità della simulazione (pixel).
+const PLAYER_DRIVE_FORCE: f32 = PLAYER_ACCELERATION_FORCE * 200.;
+/// Ampiezza dell'oscillazione, in frazioni della spinta.
+const OSCILLATION_AMPLITUDE: f32 = 2.;
+/// Forza in newton oltre la quale un segmento della coda si stacca. Una corda non
+/// può tirare più della testa che trascina la coda, quindi con il doppio della spinta
+/// massima (input più oscillazione) la coda si stacca solo negli urti, es. sbattendo
+/// sul terreno, mai per i movimenti del giocatore.
+const SEGMENT_BREAK_FORCE: f32 =
+    2. * PLAYER_DRIVE_FORCE * (1. + OSCILLATION_AMPLITUDE) / PIXELS_PER_METER;
 
-#[derive(Component)]
-struct TriggerOscillation(bool);
+#[derive(Component, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct TriggerOscillation(pub bool);
 fn move_player(
-    mut oscillate: Single<&mut TriggerOscillation, With<Player>>,
-    velocity: Single<(&mut ExternalImpulse, &mut Velocity), With<RobotHead>>,
-    rope_length: Single<&mut Robot, With<Player>>,
-    mut rope_entities: Query<&mut ImpulseJoint>,
-    kb_input: Res<ButtonInput<KeyCode>>,
+    mut players: Query<(&Head, &PlayerInput, &mut TriggerOscillation), With<Player>>,
+    mut heads: Query<&mut ExternalImpulse, With<RobotHead>>,
     time: Res<Time>,
     mut timer: ResMut<MyTimer>,
 ) {
-    let mut direction = Vec2::ZERO;
-    let mut torque_rotation = 0f32;
-    let mut rope_length = rope_length.into_inner();
-
-    if kb_input.pressed(KeyCode::ArrowRight) {
-        torque_rotation = -1.;
-    }
-    if kb_input.pressed(KeyCode::ArrowLeft) {
-        torque_rotation = 1.;
-    }
-    if kb_input.pressed(KeyCode::KeyW) {
-        direction += Vec2 { x: 0.0, y: 1. };
-    }
-    if kb_input.pressed(KeyCode::KeyS) {
-        direction += Vec2 { x: 0.0, y: -1. };
-    }
-    if kb_input.pressed(KeyCode::KeyA) {
-        direction += Vec2 { x: -1.0, y: 0. };
-    }
-    if kb_input.pressed(KeyCode::KeyD) {
-        direction += Vec2 { x: 1.0, y: 0. };
-    }
-    if direction.x.abs() + direction.y.abs() > 1. {
-        let module = direction.distance(Vec2::ZERO);
-        direction /= module;
-    }
-    let dir_angle = direction.to_angle();
     let freq_mod = 6.;
     let oscillation = Vec2 {
         x: 0.0, //(timer.0.elapsed_secs() * freq_mod).cos(),
         y: (timer.0.elapsed_secs() * freq_mod).sin(),
     };
 
-    let mut osc = oscillate.as_mut();
-    if kb_input.pressed(KeyCode::KeyK) {
-        osc.0 = !(osc.0);
+    timer.0.tick(time.delta());
+
+    for (head, input, mut osc) in &mut players {
+        let mut direction = input.movement;
+        if direction.x.abs() + direction.y.abs() > 1. {
+            let module = direction.distance(Vec2::ZERO);
+            direction /= module;
+        }
+        if input.toggle_oscillation {
+            osc.0 = !(osc.0);
+        }
+        if osc.0 {
+            direction += oscillation * OSCILLATION_AMPLITUDE;
+        }
+
+        //  dbg!(**ext_forces);
+        let Ok(mut impulse) = heads.get_mut(head.0) else {
+            continue;
+        };
+        //direction = velocity.linvel.normalize_or_zero().lerp(direction, 0.73);
+        impulse.impulse += direction * PLAYER_DRIVE_FORCE * time.delta_secs();
     }
-    if oscillate.into_inner().0 {
-        direction += oscillation * 2.;
+}
+
+/// I pezzi nuovi ricevono il [`PartColor`] del materiale con cui sono stati spawnati.
+fn add_part_colors(
+    mut commands: Commands,
+    parts: Query<(Entity, &MeshMaterial2d<ColorMaterial>), (Added<PartOf>, Without<PartColor>)>,
+    materials: Res<Assets<ColorMaterial>>,
+) {
+    for (part, material) in &parts {
+        if let Some(material) = materials.get(&material.0) {
+            commands.entity(part).insert(PartColor(material.color));
+        }
     }
-    timer.0.tick(time.delta());
+}
+
+fn apply_part_colors(
+    parts: Query<(&PartColor, &MeshMaterial2d<ColorMaterial>), Changed<PartColor>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (color, material) in &parts {
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.color = color.0;
+        }
+    }
+}
+
+/// Porta `Robot::rope_lenght` sul `RopeLength` della corda agganciata alla testa.
+fn apply_robot_rope_lengths(
+    robots: Query<(&Robot, &Head), Changed<Robot>>,
+    mut ropes: Query<(&ImpulseJoint, &mut RopeLength)>,
+) {
+    for (robot, head) in &robots {
+        for (joint, mut length) in &mut ropes {
+            if joint.parent == head.0 && length.0 != robot.rope_lenght {
+                length.0 = robot.rope_lenght;
+            }
+        }
+    }
+}
 
-    //  dbg!(**ext_forces);
-    let (mut impulse, mut velocity) = velocity.into_inner();
-    //direction = velocity.linvel.normalize_or_zero().lerp(direction, 0.73);
-    impulse.impulse += direction * PLAYER_ACCELERATION_FORCE * 200. * time.delta_secs();
+/// Quando un segmento del corpo si stacca, tutta la coda che gli sta dietro smette
+/// di far parte del player e resta nel mondo come detrito.
+fn drop_broken_tail(
+    mut commands: Commands,
+    mut broken_joints: EventReader<JointBrokenEvent>,
+    body_parts: Query<(), With<RobotBody>>,
+    joints: Query<(Entity, &ImpulseJoint)>,
+) {
+    let links: HashMap<Entity, Entity> = joints
+        .iter()
+        .map(|(entity, joint)| (joint.parent, entity))
+        .collect();
+
+    for event in broken_joints.read() {
+        if !body_parts.contains(event.entity) {
+            continue;
+        }
+        let tail = std::iter::once(event.entity).chain(joint_chain(event.entity, &links));
+        for part in tail {
+            commands
+                .entity(part)
+                .remove::<(RobotBody, PartOf)>()
+                .remove_parent_in_place()
+                // senza più il player come genitore va eliminata a parte
+                .insert(StateScoped(InRun));
+        }
+    }
 }
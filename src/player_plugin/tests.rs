@@ -0,0 +1,195 @@
+//! Regressioni della catena di segmenti: la creatura viene spawnata da `spawn_player`
+//! in un'app senza finestra e la fisica avanza a passo fisso (vedi `headless`).
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{LocalPlayers, Player, BALL_NUMS};
+use crate::{
+    headless::gameplay_app,
+    mechanical_components::joints::RopeLength,
+    robot_factory::robot_parts::{Head, RobotBody, RobotHead},
+};
+
+/// Allungamento massimo tollerato di una corda, in frazione della sua lunghezza.
+const ROPE_TOLERANCE: f32 = 0.2;
+/// Energia cinetica sotto la quale la catena è considerata ferma.
+const REST_ENERGY: f32 = 1.;
+
+type ChainPart = Or<(With<RobotHead>, With<RobotBody>)>;
+
+/// App con un solo giocatore (WASD), già dentro la partita.
+fn chain_app() -> App {
+    let mut app = gameplay_app(LocalPlayers::new(1, &[]));
+    app.finish();
+    app.cleanup();
+    // il primo update entra in `InRun` e spawna terreno e creatura
+    app.update();
+    app
+}
+
+/// Avanza di `steps` passi controllando `check` dopo ognuno.
+fn step(app: &mut App, steps: usize, mut check: impl FnMut(&mut World)) {
+    for _ in 0..steps {
+        app.update();
+        check(app.world_mut());
+    }
+}
+
+fn hold_key(app: &mut App, key: KeyCode) {
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(key);
+}
+
+fn release_key(app: &mut App, key: KeyCode) {
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .release(key);
+}
+
+fn head_position(world: &mut World) -> Vec2 {
+    let head = world
+        .query_filtered::<&Head, With<Player>>()
+        .single(world)
+        .0;
+    world
+        .get::<GlobalTransform>(head)
+        .unwrap()
+        .translation()
+        .truncate()
+}
+
+fn kinetic_energy(world: &mut World) -> f32 {
+    world
+        .query_filtered::<(&Velocity, &ColliderMassProperties), ChainPart>()
+        .iter(world)
+        .map(|(velocity, mass)| match mass {
+            ColliderMassProperties::Mass(mass) => 0.5 * mass * velocity.linvel.length_squared(),
+            _ => 0.,
+        })
+        .sum()
+}
+
+fn assert_finite(world: &mut World) {
+    for (entity, transform) in world
+        .query_filtered::<(Entity, &Transform), ChainPart>()
+        .iter(world)
+    {
+        assert!(
+            transform.translation.is_finite() && transform.rotation.is_finite(),
+            "{entity} has a non finite transform: {transform:?}"
+        );
+    }
+}
+
+fn assert_ropes_hold(world: &mut World) {
+    let ropes: Vec<(Entity, Entity, f32)> = world
+        .query::<(Entity, &ImpulseJoint, &RopeLength)>()
+        .iter(world)
+        .map(|(entity, joint, length)| (entity, joint.parent, length.0))
+        .collect();
+    for (entity, parent, length) in ropes {
+        let a = world.get::<GlobalTransform>(entity).unwrap().translation();
+        let b = world.get::<GlobalTransform>(parent).unwrap().translation();
+        let distance = a.distance(b);
+        assert!(
+            distance <= length * (1. + ROPE_TOLERANCE),
+            "rope {parent} -> {entity} stretched to {distance:.1}, max {length:.1}"
+        );
+    }
+}
+
+#[test]
+fn spawn_player_builds_the_whole_chain() {
+    let mut app = chain_app();
+    let world = app.world_mut();
+
+    assert_eq!(
+        world
+            .query_filtered::<(), With<RobotHead>>()
+            .iter(world)
+            .count(),
+        1
+    );
+    assert_eq!(
+        world
+            .query_filtered::<(), With<RobotBody>>()
+            .iter(world)
+            .count(),
+        BALL_NUMS + 1
+    );
+    // ogni segmento è legato al precedente, la testa a nessuno
+    assert_eq!(
+        world
+            .query_filtered::<(), (With<RobotBody>, With<ImpulseJoint>)>()
+            .iter(world)
+            .count(),
+        BALL_NUMS + 1
+    );
+    assert_ropes_hold(world);
+}
+
+#[test]
+fn idle_chain_stays_at_rest() {
+    let mut app = chain_app();
+    step(&mut app, 300, |world| {
+        assert_finite(world);
+        assert_ropes_hold(world);
+        let energy = kinetic_energy(world);
+        assert!(energy < REST_ENERGY, "idle chain gained energy: {energy}");
+    });
+}
+
+#[test]
+fn ropes_hold_under_constant_input() {
+    let mut app = chain_app();
+    hold_key(&mut app, KeyCode::KeyD);
+    step(&mut app, 600, |world| {
+        assert_finite(world);
+        assert_ropes_hold(world);
+    });
+}
+
+#[test]
+fn energy_decays_after_input_is_released() {
+    let mut app = chain_app();
+    hold_key(&mut app, KeyCode::KeyD);
+    step(&mut app, 120, |_| {});
+    release_key(&mut app, KeyCode::KeyD);
+
+    let released = kinetic_energy(app.world_mut());
+    assert!(released > REST_ENERGY, "the chain never moved");
+    // il damping può solo togliere energia: un po' di margine per le corde
+    step(&mut app, 300, |world| {
+        assert_finite(world);
+        let energy = kinetic_energy(world);
+        assert!(
+            energy <= released * 1.1,
+            "energy grew without input: {energy} > {released}"
+        );
+    });
+    let end = kinetic_energy(app.world_mut());
+    assert!(
+        end < released * 0.1,
+        "energy did not decay: {end} of {released}"
+    );
+}
+
+#[test]
+fn head_reaches_target_under_constant_input() {
+    let mut app = chain_app();
+    let start = head_position(app.world_mut());
+    let target = start + Vec2::new(500., 0.);
+
+    hold_key(&mut app, KeyCode::KeyD);
+    let mut reached = false;
+    step(&mut app, 600, |world| {
+        reached |= head_position(world).x >= target.x;
+    });
+    assert!(
+        reached,
+        "head stopped at {} without reaching {target}",
+        head_position(app.world_mut())
+    );
+}
@@ -0,0 +1,200 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_state::{GameRng, GameState, InRun},
+    player_plugin::{Player, PlayerInput, PlayerSet, PlayerSlot},
+};
+
+/// Passo fisso usato registrando: ogni frame avanza la fisica esattamente di tanto.
+pub const REPLAY_DT: f32 = 1. / 60.;
+
+/// Una partita registrata: seed, numero di giocatori e gli input di ogni passo di
+/// fisica. I passi con la fisica ferma (pausa, modalità foto) non vengono salvati.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub players: usize,
+    pub dt: f32,
+    /// Per ogni passo, l'input di ogni giocatore in ordine di slot.
+    pub frames: Vec<Vec<PlayerInput>>,
+}
+
+impl Replay {
+    pub fn load(path: &PathBuf) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        ron::from_str(&text).map_err(|err| err.to_string())
+    }
+
+    fn save(&self, path: &PathBuf) -> Result<(), String> {
+        let text = ron::to_string(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| err.to_string())
+    }
+}
+
+pub enum ReplayMode {
+    /// Registra la partita e la scrive nel file all'uscita.
+    Record(PathBuf),
+    /// Rigioca una partita registrata al posto di tastiera e gamepad.
+    Play(Replay),
+}
+
+/// Con SIMD o il solver parallelo di Rapier (le feature di default) lo stesso replay
+/// può dare partite diverse: serve una build `--no-default-features --features
+/// deterministic`.
+pub fn check_deterministic_build() -> Result<(), String> {
+    if cfg!(all(feature = "deterministic", not(feature = "parallel-physics"))) {
+        Ok(())
+    } else {
+        Err("replays need a deterministic build: \
+             cargo run --no-default-features --features deterministic"
+            .into())
+    }
+}
+
+/// Per riprodurre una partita identica servono lo stesso eseguibile, compilato come
+/// chiede [`check_deterministic_build`], e il passo fisso che questo plugin imposta.
+pub struct ReplayPlugin {
+    pub mode: ReplayMode,
+}
+
+#[derive(Resource)]
+struct ReplayRecorder {
+    path: PathBuf,
+    replay: Replay,
+}
+
+#[derive(Resource)]
+struct ReplayPlayback {
+    replay: Replay,
+    frame: usize,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if let Err(err) = check_deterministic_build() {
+            println!("warning: {err}");
+        }
+        let dt = match &self.mode {
+            ReplayMode::Record(_) => REPLAY_DT,
+            ReplayMode::Play(replay) => replay.dt,
+        };
+        app.insert_resource(TimestepMode::Fixed { dt, substeps: 1 })
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                dt,
+            )));
+
+        match &self.mode {
+            ReplayMode::Record(path) => {
+                app.insert_resource(ReplayRecorder {
+                    path: path.clone(),
+                    replay: Replay {
+                        seed: 0,
+                        players: 0,
+                        dt,
+                        frames: vec![],
+                    },
+                })
+                .add_systems(OnEnter(InRun), start_recording)
+                .add_systems(
+                    Update,
+                    record_inputs
+                        .after(PlayerSet::ReadInput)
+                        .before(PlayerSet::Move)
+                        .run_if(in_state(GameState::Playing)),
+                )
+                .add_systems(Last, save_recording);
+            }
+            ReplayMode::Play(replay) => {
+                app.insert_resource(GameRng::new(replay.seed))
+                    .insert_resource(ReplayPlayback {
+                        replay: replay.clone(),
+                        frame: 0,
+                    })
+                    .add_systems(OnEnter(InRun), |mut playback: ResMut<ReplayPlayback>| {
+                        playback.frame = 0;
+                    })
+                    .add_systems(
+                        Update,
+                        play_inputs
+                            .after(PlayerSet::ReadInput)
+                            .before(PlayerSet::Move)
+                            .run_if(in_state(GameState::Playing)),
+                    );
+            }
+        }
+    }
+}
+
+fn physics_running(rapier_config: &Query<&RapierConfiguration>) -> bool {
+    rapier_config
+        .iter()
+        .all(|config| config.physics_pipeline_active)
+}
+
+/// Ogni partita ricomincia la registrazione: il file contiene l'ultima.
+fn start_recording(mut recorder: ResMut<ReplayRecorder>, rng: Res<GameRng>) {
+    recorder.replay.seed = rng.seed;
+    recorder.replay.frames.clear();
+}
+
+fn record_inputs(
+    mut recorder: ResMut<ReplayRecorder>,
+    players: Query<(&PlayerSlot, &PlayerInput), With<Player>>,
+    rapier_config: Query<&RapierConfiguration>,
+) {
+    if !physics_running(&rapier_config) {
+        return;
+    }
+    let count = players
+        .iter()
+        .map(|(slot, _)| slot.0 + 1)
+        .max()
+        .unwrap_or(0);
+    let mut frame = vec![PlayerInput::default(); count];
+    for (slot, input) in &players {
+        frame[slot.0] = *input;
+    }
+    recorder.replay.players = recorder.replay.players.max(count);
+    recorder.replay.frames.push(frame);
+}
+
+fn save_recording(mut exit: EventReader<AppExit>, recorder: Res<ReplayRecorder>) {
+    if exit.read().next().is_none() {
+        return;
+    }
+    match recorder.replay.save(&recorder.path) {
+        Ok(()) => println!(
+            "replay saved to {} ({} frames, seed {})",
+            recorder.path.display(),
+            recorder.replay.frames.len(),
+            recorder.replay.seed
+        ),
+        Err(err) => println!("could not save replay {}: {err}", recorder.path.display()),
+    }
+}
+
+/// Sovrascrive gli input letti da tastiera e gamepad con quelli registrati; finito
+/// il replay i comandi tornano ai giocatori.
+fn play_inputs(
+    mut playback: ResMut<ReplayPlayback>,
+    mut players: Query<(&PlayerSlot, &mut PlayerInput), With<Player>>,
+    rapier_config: Query<&RapierConfiguration>,
+) {
+    if !physics_running(&rapier_config) {
+        return;
+    }
+    let Some(frame) = playback.replay.frames.get(playback.frame) else {
+        return;
+    };
+    for (slot, mut input) in &mut players {
+        *input = frame.get(slot.0).copied().unwrap_or_default();
+    }
+    playback.frame += 1;
+    if playback.frame == playback.replay.frames.len() {
+        println!("replay finished after {} frames", playback.frame);
+    }
+}
@@ -0,0 +1,314 @@
+use std::{f32::consts::TAU, fs, path::Path};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::robot_parts::{Head, PartOf, Robot, RobotBody, RobotHead};
+use crate::{
+    game_state::InRun,
+    mechanical_components::generic::{
+        GenericMechanicalComponentBundle, MyPosition, MyRigidBody, Shape,
+    },
+};
+
+/// Descrizione serializzabile di un robot: la parte 0 è la testa, ogni altra parte è
+/// agganciata con un giunto rotoidale motorizzato a una parte che la precede.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotBlueprint {
+    pub parts: Vec<PartGene>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartGene {
+    pub shape: ShapeGene,
+    pub mass: f32,
+    pub hue: f32,
+    /// Indice della parte a cui è agganciata, sempre minore del proprio indice.
+    /// Ignorato per la testa.
+    pub parent: usize,
+    pub parent_anchor: Vec2,
+    pub self_anchor: Vec2,
+    pub motor: JointMotor,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum ShapeGene {
+    Rect { width: f32, heigt: f32 },
+    Ball { radius: f32 },
+}
+
+/// Motore del giunto che aggancia l'entità al genitore: la velocità angolare
+/// obiettivo oscilla come `amplitude * sin(TAU * frequency * t + phase)`.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize, Reflect)]
+#[reflect(Component)]
+pub struct JointMotor {
+    pub amplitude: f32,
+    pub frequency: f32,
+    pub phase: f32,
+}
+
+/// Entità create da [`spawn_blueprint`], `parts[0]` è la testa.
+pub struct SpawnedBlueprint {
+    pub robot: Entity,
+    pub parts: Vec<Entity>,
+}
+
+/// Robot (es. il migliore dell'evoluzione) aggiunto a ogni partita accanto ai
+/// giocatori, si muove da solo con i suoi motori.
+#[derive(Resource, Clone)]
+pub struct ExtraCreature(pub RobotBlueprint);
+
+/// A sinistra dei giocatori, poco sopra il terreno.
+const EXTRA_CREATURE_POSITION: MyPosition = MyPosition { x: -800., y: -750. };
+
+const MIN_PARTS: usize = 2;
+const MAX_PARTS: usize = 8;
+const MOTOR_FACTOR: f32 = 1.0;
+
+pub struct BlueprintPlugin;
+impl Plugin for BlueprintPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<JointMotor>()
+            .add_systems(
+                OnEnter(InRun),
+                spawn_extra_creature.run_if(resource_exists::<ExtraCreature>),
+            )
+            .add_systems(Update, drive_joint_motors);
+    }
+}
+
+impl ShapeGene {
+    fn to_shape(self) -> Shape {
+        match self {
+            ShapeGene::Rect { width, heigt } => Shape::Rect { width, heigt },
+            ShapeGene::Ball { radius } => Shape::Ball { radius },
+        }
+    }
+
+    /// Semi-estensione della forma, usata per tenere le ancore sul bordo della parte.
+    fn half_extents(self) -> Vec2 {
+        match self {
+            ShapeGene::Rect { width, heigt } => Vec2::new(width, heigt) / 2.,
+            ShapeGene::Ball { radius } => Vec2::splat(radius),
+        }
+    }
+
+    fn random(rng: &mut impl Rng) -> Self {
+        if rng.gen_bool(0.5) {
+            ShapeGene::Rect {
+                width: rng.gen_range(20.0..120.0),
+                heigt: rng.gen_range(20.0..120.0),
+            }
+        } else {
+            ShapeGene::Ball {
+                radius: rng.gen_range(10.0..60.0),
+            }
+        }
+    }
+
+    fn mutate(self, rng: &mut impl Rng, strength: f32) -> Self {
+        match self {
+            ShapeGene::Rect { width, heigt } => ShapeGene::Rect {
+                width: jitter(rng, width, strength).max(10.),
+                heigt: jitter(rng, heigt, strength).max(10.),
+            },
+            ShapeGene::Ball { radius } => ShapeGene::Ball {
+                radius: jitter(rng, radius, strength).max(5.),
+            },
+        }
+    }
+}
+
+impl PartGene {
+    fn random(parent: usize, parent_shape: Option<ShapeGene>, rng: &mut impl Rng) -> Self {
+        let shape = ShapeGene::random(rng);
+        let parent_extents = parent_shape.map_or(Vec2::ZERO, ShapeGene::half_extents);
+        let self_extents = shape.half_extents();
+        Self {
+            shape,
+            mass: rng.gen_range(0.05..0.5),
+            hue: rng.gen_range(0.0..360.0),
+            parent,
+            parent_anchor: random_anchor(parent_extents, rng),
+            self_anchor: random_anchor(self_extents, rng),
+            motor: JointMotor {
+                amplitude: rng.gen_range(0.0..10.0),
+                frequency: rng.gen_range(0.2..2.0),
+                phase: rng.gen_range(0.0..TAU),
+            },
+        }
+    }
+}
+
+/// Scala `value` di un fattore casuale in `1 ± strength`.
+fn jitter(rng: &mut impl Rng, value: f32, strength: f32) -> f32 {
+    value * (1. + rng.gen_range(-strength..=strength))
+}
+
+fn random_anchor(half_extents: Vec2, rng: &mut impl Rng) -> Vec2 {
+    Vec2::new(
+        rng.gen_range(-1.0..=1.0) * half_extents.x,
+        rng.gen_range(-1.0..=1.0) * half_extents.y,
+    )
+}
+
+impl RobotBlueprint {
+    /// Legge un blueprint salvato dall'evoluzione (es. `evolution/best.ron`).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        ron::from_str(&text).map_err(|err| err.to_string())
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let part_nums = rng.gen_range(MIN_PARTS..=MAX_PARTS);
+        let mut parts: Vec<PartGene> = Vec::with_capacity(part_nums);
+        for i in 0..part_nums {
+            let parent = if i == 0 { 0 } else { rng.gen_range(0..i) };
+            let parent_shape = (i > 0).then(|| parts[parent].shape);
+            parts.push(PartGene::random(parent, parent_shape, rng));
+        }
+        Self { parts }
+    }
+
+    /// Perturba ogni gene con probabilità `rate`; con la stessa probabilità aggiunge
+    /// o rimuove l'ultima parte.
+    pub fn mutate(&mut self, rng: &mut impl Rng, rate: f64) {
+        let strength = 0.2;
+        for part in &mut self.parts {
+            if rng.gen_bool(rate) {
+                part.shape = part.shape.mutate(rng, strength);
+            }
+            if rng.gen_bool(rate) {
+                part.mass = jitter(rng, part.mass, strength).max(0.01);
+            }
+            if rng.gen_bool(rate) {
+                part.parent_anchor = jitter(rng, 1., strength) * part.parent_anchor;
+                part.self_anchor = jitter(rng, 1., strength) * part.self_anchor;
+            }
+            if rng.gen_bool(rate) {
+                part.motor.amplitude += rng.gen_range(-1.0..=1.0);
+                part.motor.frequency = (part.motor.frequency + rng.gen_range(-0.2..=0.2)).max(0.05);
+                part.motor.phase = (part.motor.phase + rng.gen_range(-0.5..=0.5)).rem_euclid(TAU);
+            }
+        }
+        if rng.gen_bool(rate) {
+            if self.parts.len() < MAX_PARTS && rng.gen_bool(0.5) {
+                let parent = rng.gen_range(0..self.parts.len());
+                let part = PartGene::random(parent, Some(self.parts[parent].shape), rng);
+                self.parts.push(part);
+            } else if self.parts.len() > MIN_PARTS {
+                self.parts.pop();
+            }
+        }
+    }
+
+    /// Crossover uniforme parte per parte; la lunghezza viene presa da uno dei due genitori.
+    pub fn crossover(&self, other: &Self, rng: &mut impl Rng) -> Self {
+        let (long, short) = if self.parts.len() >= other.parts.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let len = if rng.gen_bool(0.5) {
+            long.parts.len()
+        } else {
+            short.parts.len()
+        };
+        let parts = (0..len)
+            .map(|i| match short.parts.get(i) {
+                Some(part) if rng.gen_bool(0.5) => part.clone(),
+                _ => long.parts[i].clone(),
+            })
+            .collect();
+        Self { parts }
+    }
+}
+
+pub fn spawn_blueprint(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    blueprint: &RobotBlueprint,
+    position: MyPosition,
+) -> SpawnedBlueprint {
+    let robot = commands
+        .spawn((
+            Robot { rope_lenght: 0. },
+            position.to_transform(),
+            Visibility::default(),
+        ))
+        .id();
+
+    let mut parts: Vec<Entity> = Vec::with_capacity(blueprint.parts.len());
+    let mut part_positions: Vec<Vec2> = Vec::with_capacity(blueprint.parts.len());
+    for (i, gene) in blueprint.parts.iter().enumerate() {
+        // le parti nascono già sovrapposte alle ancore, così i giunti partono a riposo
+        let local_pos = if i == 0 {
+            Vec2::ZERO
+        } else {
+            part_positions[gene.parent] + gene.parent_anchor - gene.self_anchor
+        };
+        part_positions.push(local_pos);
+
+        let bundle = GenericMechanicalComponentBundle::new(
+            MyRigidBody::Dynamic { mass: gene.mass },
+            gene.shape.to_shape(),
+            Color::hsl(gene.hue, 0.95, 0.6),
+            Transform::from_translation(local_pos.extend(0.)),
+            meshes,
+            materials,
+        );
+        let part = if i == 0 {
+            commands.spawn((RobotHead, PartOf(robot), bundle)).id()
+        } else {
+            let joint = RevoluteJointBuilder::new()
+                .local_anchor1(gene.parent_anchor)
+                .local_anchor2(gene.self_anchor)
+                .motor_velocity(0., MOTOR_FACTOR);
+            commands
+                .spawn((
+                    RobotBody,
+                    PartOf(robot),
+                    bundle,
+                    gene.motor,
+                    ImpulseJoint::new(parts[gene.parent], joint),
+                ))
+                .id()
+        };
+        parts.push(part);
+    }
+    commands
+        .entity(robot)
+        .add_children(&parts)
+        .insert(Head(parts[0]));
+
+    SpawnedBlueprint { robot, parts }
+}
+
+fn spawn_extra_creature(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    creature: Res<ExtraCreature>,
+) {
+    let spawned = spawn_blueprint(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &creature.0,
+        EXTRA_CREATURE_POSITION,
+    );
+    commands.entity(spawned.robot).insert(StateScoped(InRun));
+}
+
+fn drive_joint_motors(mut motors: Query<(&JointMotor, &mut ImpulseJoint)>, time: Res<Time>) {
+    let t = time.elapsed_secs();
+    for (motor, mut joint) in &mut motors {
+        let target_vel = motor.amplitude * (TAU * motor.frequency * t + motor.phase).sin();
+        if let TypedJoint::RevoluteJoint(revolute) = &mut joint.data {
+            revolute.set_motor_velocity(target_vel, MOTOR_FACTOR);
+        }
+    }
+}
@@ -1,10 +1,11 @@
+pub mod blueprint;
 pub mod robot_parts;
 
-use bevy::{color::palettes::tailwind::BLUE_950, prelude::*};
+use bevy::{color::palettes::tailwind::BLUE_950, prelude::*, utils::HashMap};
 use bevy_rapier2d::prelude::{GenericJoint, ImpulseJoint, RevoluteJoint, RevoluteJointBuilder};
 use robot_parts::*;
 
-use crate::mechanical_components::generic::*;
+use crate::mechanical_components::{generic::*, joints::Breakable};
 
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
@@ -25,11 +26,31 @@ pub fn spawn_robot(
 
     let head = spawn_robot_head(commands, meshes, materials);
     let leg = spawn_robot_leg(commands, meshes, materials);
-    commands.entity(robot).add_child(head);
+    commands.entity(robot).add_child(head).insert(Head(head));
+    commands.entity(head).insert(PartOf(robot));
+    commands.entity(leg).insert(PartOf(robot));
 
     let joint = RevoluteJointBuilder::new()
         .local_anchor1(Vec2 { x: 100., y: 100. })
         .local_anchor2(Vec2 { x: 0., y: 30. });
-    commands.entity(leg).insert(ImpulseJoint::new(head, joint));
+    commands.entity(leg).insert((
+        ImpulseJoint::new(head, joint),
+        Breakable {
+            max_force: 500.,
+            max_torque: 2.,
+        },
+    ));
     robot
 }
+
+/// Segue la catena di `ImpulseJoint` a partire da `root` e ritorna i segmenti in ordine
+/// (escluso `root`). `links` mappa ogni corpo al corpo che vi è agganciato.
+pub fn joint_chain(root: Entity, links: &HashMap<Entity, Entity>) -> Vec<Entity> {
+    let mut chain = vec![];
+    let mut current = root;
+    while let Some(&next) = links.get(&current) {
+        chain.push(next);
+        current = next;
+    }
+    chain
+}
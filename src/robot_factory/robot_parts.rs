@@ -2,18 +2,39 @@ use bevy::{color::palettes::tailwind::BLUE_950, prelude::*};
 
 use super::{GenericMechanicalComponentBundle, MyPosition, MyRigidBody, Shape};
 
-#[derive(Component)]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct Robot{
+    /// Corda tra la testa e il primo segmento: cambiandola (es. dall'inspector) la
+    /// corda viene aggiornata. I robot a giunti rotoidali non la usano.
     pub rope_lenght: f32
 } // Tag per l'entità principale del robot.
 
 
-#[derive(Component, Default)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct RobotHead;
 
-#[derive(Component, Default)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct RobotBody;
 
+/// Sull'entità principale (Player o Robot), punta alla sua testa.
+#[derive(Component, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct Head(pub Entity);
+
+/// Su ogni pezzo della creatura, punta all'entità principale a cui appartiene.
+#[derive(Component, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct PartOf(pub Entity);
+
+/// Colore di un pezzo della creatura: cambiandolo (es. dall'inspector) il materiale
+/// del pezzo viene aggiornato.
+#[derive(Component, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct PartColor(pub Color);
+
 pub fn spawn_robot_head(
     command: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
@@ -0,0 +1,279 @@
+use bevy::{
+    dev_tools::fps_overlay::FpsOverlayConfig,
+    prelude::*,
+    window::{MonitorSelection, PresentMode, PrimaryWindow, WindowMode},
+};
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::save_config,
+    player_plugin::{InputBinding, KeyboardScheme, LocalPlayers, PlayerSlot},
+};
+
+pub const SETTINGS_FILE: &str = "settings.ron";
+
+/// Risoluzioni proposte nel menu, in pixel logici.
+const RESOLUTIONS: [UVec2; 4] = [
+    UVec2::new(1280, 720),
+    UVec2::new(1600, 900),
+    UVec2::new(1920, 1080),
+    UVec2::new(2560, 1440),
+];
+
+/// Impostazioni del gioco, salvate in [`SETTINGS_FILE`] e lette all'avvio.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    pub display_mode: DisplayMode,
+    pub vsync: bool,
+    /// Dimensioni della finestra in modalità finestra, in pixel logici.
+    pub resolution: UVec2,
+    pub show_fps: bool,
+    pub fps_font_size: f32,
+    /// Volume generale, da 0 a 1.
+    pub master_volume: f32,
+    /// Dispositivo di ogni slot giocatore (vedi [`LocalPlayers`]).
+    pub bindings: Vec<InputBinding>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayMode {
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            display_mode: DisplayMode::Windowed,
+            vsync: true,
+            resolution: RESOLUTIONS[0],
+            show_fps: true,
+            fps_font_size: 42.,
+            master_volume: 1.,
+            bindings: (0..LocalPlayers::MAX).map(InputBinding::for_slot).collect(),
+        }
+    }
+}
+
+impl From<DisplayMode> for WindowMode {
+    fn from(mode: DisplayMode) -> Self {
+        match mode {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::Borderless => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+            DisplayMode::Fullscreen => WindowMode::Fullscreen(MonitorSelection::Current),
+        }
+    }
+}
+
+impl GameSettings {
+    /// Finestra principale da passare a `WindowPlugin`.
+    pub fn window(&self) -> Window {
+        let mut window = Window::default();
+        self.apply_window(&mut window);
+        window
+    }
+
+    fn apply_window(&self, window: &mut Window) {
+        window.mode = self.display_mode.into();
+        window.present_mode = self.present_mode();
+        window
+            .resolution
+            .set(self.resolution.x as f32, self.resolution.y as f32);
+    }
+
+    /// Come [`Self::apply_window`], ma solo per i campi diversi da `previous`: il
+    /// resto della finestra resta com'è, es. dopo un ridimensionamento a mano.
+    fn apply_window_changes(&self, previous: &GameSettings, window: &mut Window) {
+        if self.display_mode != previous.display_mode {
+            window.mode = self.display_mode.into();
+        }
+        if self.vsync != previous.vsync {
+            window.present_mode = self.present_mode();
+        }
+        if self.resolution != previous.resolution {
+            window
+                .resolution
+                .set(self.resolution.x as f32, self.resolution.y as f32);
+        }
+    }
+
+    fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+}
+
+/// Finestra delle impostazioni, aperta/chiusa con F1.
+#[derive(Resource, Default)]
+struct SettingsPanel {
+    open: bool,
+    /// Modifiche non ancora salvate su disco.
+    dirty: bool,
+}
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameSettings>()
+            .init_resource::<SettingsPanel>()
+            .add_systems(Update, (settings_panel, apply_settings).chain());
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+    }
+}
+
+/// Porta le impostazioni su finestra, overlay degli FPS, volume e giocatori quando
+/// cambiano. All'avvio finestra e overlay sono già configurati da `main`. `applied`
+/// sono le impostazioni dell'ultima volta: la finestra viene toccata solo se
+/// cambiano i suoi campi.
+fn apply_settings(
+    settings: Res<GameSettings>,
+    mut applied: Local<Option<GameSettings>>,
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+    fps_overlay: Option<ResMut<FpsOverlayConfig>>,
+    global_volume: Option<ResMut<GlobalVolume>>,
+    mut local_players: ResMut<LocalPlayers>,
+    mut players: Query<(&PlayerSlot, &mut InputBinding)>,
+) {
+    if settings.is_added() {
+        *applied = Some(settings.clone());
+        return;
+    }
+    if !settings.is_changed() {
+        return;
+    }
+    match applied.replace(settings.clone()) {
+        Some(previous) => settings.apply_window_changes(&previous, &mut window),
+        None => settings.apply_window(&mut window),
+    }
+    if let Some(mut fps_overlay) = fps_overlay {
+        fps_overlay.enabled = settings.show_fps;
+        fps_overlay.text_config.font_size = settings.fps_font_size;
+    }
+    if let Some(mut global_volume) = global_volume {
+        *global_volume = GlobalVolume::new(settings.master_volume);
+    }
+
+    let binding = |slot: usize| {
+        settings
+            .bindings
+            .get(slot)
+            .copied()
+            .unwrap_or_else(|| InputBinding::for_slot(slot))
+    };
+    for (slot, local_binding) in local_players.bindings.iter_mut().enumerate() {
+        *local_binding = binding(slot);
+    }
+    for (slot, mut player_binding) in &mut players {
+        *player_binding = binding(slot.0);
+    }
+}
+
+fn binding_label(binding: InputBinding) -> String {
+    match binding {
+        InputBinding::Keyboard(KeyboardScheme::Wasd) => "Tastiera WASD (K)".into(),
+        InputBinding::Keyboard(KeyboardScheme::Arrows) => "Tastiera frecce (Shift dx)".into(),
+        InputBinding::Keyboard(KeyboardScheme::Numpad) => "Tastierino numerico (0)".into(),
+        InputBinding::Gamepad(index) => format!("Gamepad {}", index + 1),
+    }
+}
+
+fn settings_panel(
+    mut contexts: EguiContexts,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut panel: ResMut<SettingsPanel>,
+    mut settings: ResMut<GameSettings>,
+) {
+    if kb_input.just_pressed(KeyCode::F1) {
+        panel.open = !panel.open;
+        // chiudendo il pannello le modifiche vengono salvate
+        if !panel.open && panel.dirty {
+            save_config(SETTINGS_FILE, &*settings);
+            panel.dirty = false;
+        }
+    }
+    if !panel.open {
+        return;
+    }
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    // si lavora su una copia, così la risorsa risulta cambiata solo se lo è davvero
+    let mut edited = settings.clone();
+    egui::Window::new("Impostazioni").show(ctx, |ui| {
+        ui.heading("Video");
+        egui::ComboBox::from_label("modalità")
+            .selected_text(format!("{:?}", edited.display_mode))
+            .show_ui(ui, |ui| {
+                for mode in [
+                    DisplayMode::Windowed,
+                    DisplayMode::Borderless,
+                    DisplayMode::Fullscreen,
+                ] {
+                    ui.selectable_value(&mut edited.display_mode, mode, format!("{mode:?}"));
+                }
+            });
+        egui::ComboBox::from_label("risoluzione")
+            .selected_text(format!("{}x{}", edited.resolution.x, edited.resolution.y))
+            .show_ui(ui, |ui| {
+                for resolution in RESOLUTIONS {
+                    ui.selectable_value(
+                        &mut edited.resolution,
+                        resolution,
+                        format!("{}x{}", resolution.x, resolution.y),
+                    );
+                }
+            });
+        ui.checkbox(&mut edited.vsync, "vsync");
+        ui.checkbox(&mut edited.show_fps, "mostra FPS");
+        ui.add(egui::Slider::new(&mut edited.fps_font_size, 12.0..=64.0).text("dimensione FPS"));
+
+        ui.heading("Audio");
+        ui.add(egui::Slider::new(&mut edited.master_volume, 0.0..=1.0).text("volume"));
+
+        ui.heading("Controlli");
+        while edited.bindings.len() < LocalPlayers::MAX {
+            let slot = edited.bindings.len();
+            edited.bindings.push(InputBinding::for_slot(slot));
+        }
+        for (slot, binding) in edited.bindings.iter_mut().enumerate() {
+            egui::ComboBox::from_label(format!("giocatore {}", slot + 1))
+                .selected_text(binding_label(*binding))
+                .show_ui(ui, |ui| {
+                    let keyboards = [
+                        KeyboardScheme::Wasd,
+                        KeyboardScheme::Arrows,
+                        KeyboardScheme::Numpad,
+                    ]
+                    .map(InputBinding::Keyboard);
+                    let gamepads = (0..LocalPlayers::MAX).map(InputBinding::Gamepad);
+                    for option in keyboards.into_iter().chain(gamepads) {
+                        ui.selectable_value(binding, option, binding_label(option));
+                    }
+                });
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Salva").clicked() {
+                save_config(SETTINGS_FILE, &edited);
+                panel.dirty = false;
+            }
+            if ui.button("Default").clicked() {
+                edited = GameSettings::default();
+            }
+        });
+    });
+
+    if edited != *settings {
+        *settings = edited;
+        panel.dirty = true;
+    }
+}
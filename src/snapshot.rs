@@ -0,0 +1,641 @@
+use std::{fs, path::Path};
+
+use bevy::{ecs::query::QueryData, prelude::*, utils::HashMap};
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_state::{GameState, InRun},
+    mechanical_components::{
+        generic::{GenericMechanicalComponentBundle, MyRigidBody, Shape},
+        joints::{Breakable, RopeLength},
+    },
+    player_plugin::{
+        spawn_player_root, InputBinding, LocalPlayers, PlayerSlot, TriggerOscillation,
+    },
+    robot_factory::{
+        blueprint::JointMotor,
+        robot_parts::{Head, PartOf, Robot, RobotBody, RobotHead},
+    },
+    terrain_plugin::{spawn_terrain_cubes, Cube, LevelBounds},
+};
+
+/// F9 salva qui, F10 ricarica. Il file si può passare a chi deve riprodurre un
+/// problema di fisica.
+pub const SNAPSHOT_FILE: &str = "snapshot.ron";
+
+/// Stato completo di una partita: terreno, creature, detriti e stato del gioco.
+///
+/// I pezzi si riferiscono tra loro per indice in `parts`, così i joint vengono
+/// ricostruiti tra le entità giuste. Lo stato interno del solver di Rapier (contatti
+/// già risolti, warm start) non viene salvato: il primo passo dopo il caricamento
+/// può differire di poco da quello originale.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub state: GameState,
+    pub level: Option<Rect>,
+    pub terrain: Vec<CubeSnapshot>,
+    pub creatures: Vec<CreatureSnapshot>,
+    pub parts: Vec<PartSnapshot>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CubeSnapshot {
+    pub transform: Transform,
+    pub color: Color,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreatureSnapshot {
+    /// `None` per i robot senza giocatore (es. quello di `--creature`).
+    pub slot: Option<usize>,
+    pub transform: Transform,
+    pub rope_lenght: f32,
+    pub oscillation: bool,
+    /// Indice della testa in `parts`.
+    pub head: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PartKind {
+    Head,
+    Body,
+    /// Coda staccata da una creatura, non appartiene più a nessuno.
+    Debris,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum PartShape {
+    Ball { radius: f32 },
+    Rect { width: f32, height: f32 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartSnapshot {
+    pub kind: PartKind,
+    /// Indice in `creatures`, `None` per i detriti.
+    pub creature: Option<usize>,
+    pub shape: PartShape,
+    /// `None` per i corpi fissi.
+    pub mass: Option<PartMass>,
+    pub color: Color,
+    /// Relativo alla creatura per testa e corpo, assoluto per i detriti.
+    pub transform: Transform,
+    pub linvel: Vec2,
+    pub angvel: f32,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    pub gravity_scale: f32,
+    pub joint: Option<JointSnapshot>,
+}
+
+/// Come `ColliderMassProperties`, che non è serializzabile.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum PartMass {
+    Density(f32),
+    Mass(f32),
+    Properties {
+        local_center_of_mass: Vec2,
+        mass: f32,
+        principal_inertia: f32,
+    },
+}
+
+/// Joint verso il pezzo `parent` (indice in `parts`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JointSnapshot {
+    pub parent: usize,
+    pub anchor1: Vec2,
+    pub anchor2: Vec2,
+    pub kind: JointKind,
+    pub breakable: Option<Breakable>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum JointKind {
+    /// Corda delle creature dei giocatori.
+    Rope { max_distance: f32 },
+    /// Giunto rotoidale dei robot, con il motore di Rapier e il [`JointMotor`] che lo
+    /// guida.
+    Revolute {
+        limits: Option<[f32; 2]>,
+        motor: Option<MotorSnapshot>,
+        oscillator: Option<JointMotor>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct MotorSnapshot {
+    pub target_pos: f32,
+    pub target_vel: f32,
+    pub stiffness: f32,
+    pub damping: f32,
+    pub max_force: f32,
+}
+
+impl From<ColliderMassProperties> for PartMass {
+    fn from(mass: ColliderMassProperties) -> Self {
+        match mass {
+            ColliderMassProperties::Density(density) => PartMass::Density(density),
+            ColliderMassProperties::Mass(mass) => PartMass::Mass(mass),
+            ColliderMassProperties::MassProperties(properties) => PartMass::Properties {
+                local_center_of_mass: properties.local_center_of_mass,
+                mass: properties.mass,
+                principal_inertia: properties.principal_inertia,
+            },
+        }
+    }
+}
+
+impl From<PartMass> for ColliderMassProperties {
+    fn from(mass: PartMass) -> Self {
+        match mass {
+            PartMass::Density(density) => ColliderMassProperties::Density(density),
+            PartMass::Mass(mass) => ColliderMassProperties::Mass(mass),
+            PartMass::Properties {
+                local_center_of_mass,
+                mass,
+                principal_inertia,
+            } => ColliderMassProperties::MassProperties(MassProperties {
+                local_center_of_mass,
+                mass,
+                principal_inertia,
+            }),
+        }
+    }
+}
+
+impl WorldSnapshot {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        ron::from_str(&text).map_err(|err| err.to_string())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| err.to_string())
+    }
+}
+
+/// Partita da cui parte ogni run al posto di quella generata (`--level`), anche
+/// ricominciando.
+#[derive(Resource)]
+pub struct StartingLevel(pub WorldSnapshot);
+
+/// Il run appena iniziato va ancora sostituito con [`StartingLevel`].
+#[derive(Resource, Default)]
+struct LevelPending(bool);
+
+pub struct SnapshotPlugin;
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelPending>()
+            .add_systems(
+                OnEnter(InRun),
+                (|mut pending: ResMut<LevelPending>| pending.0 = true)
+                    .run_if(resource_exists::<StartingLevel>),
+            )
+            .add_systems(
+                Update,
+                (
+                    apply_starting_level.run_if(resource_exists::<StartingLevel>),
+                    save_snapshot,
+                    load_snapshot,
+                )
+                    .run_if(in_state(InRun)),
+            );
+    }
+}
+
+#[derive(QueryData)]
+struct PartData {
+    entity: Entity,
+    transform: &'static Transform,
+    collider: &'static Collider,
+    mass: &'static ColliderMassProperties,
+    rigid_body: &'static RigidBody,
+    material: &'static MeshMaterial2d<ColorMaterial>,
+    velocity: &'static Velocity,
+    damping: &'static Damping,
+    gravity_scale: &'static GravityScale,
+    joint: Option<&'static ImpulseJoint>,
+    rope_length: Option<&'static RopeLength>,
+    oscillator: Option<&'static JointMotor>,
+    breakable: Option<&'static Breakable>,
+    part_of: Option<&'static PartOf>,
+    is_head: Has<RobotHead>,
+    is_body: Has<RobotBody>,
+    in_run: Has<StateScoped<InRun>>,
+}
+
+fn save_snapshot(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    level: Option<Res<LevelBounds>>,
+    cubes: Query<(&Transform, &Cube)>,
+    robots: Query<(
+        Entity,
+        Option<&PlayerSlot>,
+        &Transform,
+        &Robot,
+        Option<&TriggerOscillation>,
+        &Head,
+    )>,
+    parts: Query<PartData, Without<Cube>>,
+    materials: Res<Assets<ColorMaterial>>,
+) {
+    if !kb_input.just_pressed(KeyCode::F9) {
+        return;
+    }
+
+    // i pezzi delle creature e i detriti della partita; altri corpi (es. esempi) no
+    let parts: Vec<(PartDataItem, PartShape)> = parts
+        .iter()
+        .filter(|part| part.part_of.is_some() || part.in_run)
+        .filter_map(|part| match part_shape(part.collider) {
+            Some(shape) => Some((part, shape)),
+            None => {
+                println!(
+                    "snapshot: skipping {} with unsupported collider",
+                    part.entity
+                );
+                None
+            }
+        })
+        .collect();
+    let part_index: HashMap<Entity, usize> = parts
+        .iter()
+        .enumerate()
+        .map(|(i, (part, _))| (part.entity, i))
+        .collect();
+
+    let creatures: Vec<(Entity, CreatureSnapshot)> = robots
+        .iter()
+        .filter_map(|(entity, slot, transform, robot, oscillation, head)| {
+            let creature = CreatureSnapshot {
+                slot: slot.map(|slot| slot.0),
+                transform: *transform,
+                rope_lenght: robot.rope_lenght,
+                oscillation: oscillation.is_some_and(|oscillation| oscillation.0),
+                head: *part_index.get(&head.0)?,
+            };
+            Some((entity, creature))
+        })
+        .collect();
+    let creature_index: HashMap<Entity, usize> = creatures
+        .iter()
+        .enumerate()
+        .map(|(i, (player, _))| (*player, i))
+        .collect();
+
+    let parts = parts
+        .iter()
+        .map(|(part, shape)| {
+            let mass = match part.rigid_body {
+                RigidBody::Fixed => None,
+                _ => Some(PartMass::from(*part.mass)),
+            };
+            let kind = if part.is_head {
+                PartKind::Head
+            } else if part.is_body {
+                PartKind::Body
+            } else {
+                PartKind::Debris
+            };
+            let joint = part.joint.and_then(|joint| {
+                let (anchor1, anchor2, kind) = match &joint.data {
+                    TypedJoint::RopeJoint(rope) => (
+                        rope.local_anchor1(),
+                        rope.local_anchor2(),
+                        JointKind::Rope {
+                            max_distance: part
+                                .rope_length
+                                .map_or(rope.max_distance(), |length| length.0),
+                        },
+                    ),
+                    TypedJoint::RevoluteJoint(revolute) => (
+                        revolute.local_anchor1(),
+                        revolute.local_anchor2(),
+                        JointKind::Revolute {
+                            limits: revolute.limits().map(|limits| [limits.min, limits.max]),
+                            motor: revolute.motor().map(|motor| MotorSnapshot {
+                                target_pos: motor.target_pos,
+                                target_vel: motor.target_vel,
+                                stiffness: motor.stiffness,
+                                damping: motor.damping,
+                                max_force: motor.max_force,
+                            }),
+                            oscillator: part.oscillator.copied(),
+                        },
+                    ),
+                    _ => {
+                        println!(
+                            "snapshot: only rope and revolute joints are saved ({})",
+                            part.entity
+                        );
+                        return None;
+                    }
+                };
+                Some(JointSnapshot {
+                    parent: *part_index.get(&joint.parent)?,
+                    anchor1,
+                    anchor2,
+                    kind,
+                    breakable: part.breakable.copied(),
+                })
+            });
+            PartSnapshot {
+                kind,
+                creature: part
+                    .part_of
+                    .and_then(|part_of| creature_index.get(&part_of.0).copied()),
+                shape: *shape,
+                mass,
+                color: materials
+                    .get(&part.material.0)
+                    .map_or(Color::WHITE, |material| material.color),
+                transform: *part.transform,
+                linvel: part.velocity.linvel,
+                angvel: part.velocity.angvel,
+                linear_damping: part.damping.linear_damping,
+                angular_damping: part.damping.angular_damping,
+                gravity_scale: part.gravity_scale.0,
+                joint,
+            }
+        })
+        .collect();
+
+    let snapshot = WorldSnapshot {
+        state: *state.get(),
+        level: level.map(|level| level.0),
+        terrain: cubes
+            .iter()
+            .map(|(transform, cube)| CubeSnapshot {
+                transform: *transform,
+                color: cube.color(),
+            })
+            .collect(),
+        creatures: creatures
+            .into_iter()
+            .map(|(_, creature)| creature)
+            .collect(),
+        parts,
+    };
+    match snapshot.save(SNAPSHOT_FILE) {
+        Ok(()) => println!("snapshot saved to {SNAPSHOT_FILE}"),
+        Err(err) => println!("could not save snapshot {SNAPSHOT_FILE}: {err}"),
+    }
+}
+
+fn part_shape(collider: &Collider) -> Option<PartShape> {
+    if let Some(ball) = collider.as_ball() {
+        return Some(PartShape::Ball {
+            radius: ball.radius(),
+        });
+    }
+    let half = collider.as_cuboid()?.half_extents();
+    Some(PartShape::Rect {
+        width: half.x * 2.,
+        height: half.y * 2.,
+    })
+}
+
+fn load_snapshot(
+    mut commands: Commands,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    in_run: Query<Entity, With<StateScoped<InRun>>>,
+    local_players: Res<LocalPlayers>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !kb_input.just_pressed(KeyCode::F10) {
+        return;
+    }
+    let snapshot = match WorldSnapshot::load(SNAPSHOT_FILE) {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            println!("could not load snapshot {SNAPSHOT_FILE}: {err}");
+            return;
+        }
+    };
+
+    restore_snapshot(
+        &mut commands,
+        &snapshot,
+        &in_run,
+        &local_players,
+        &mut meshes,
+        &mut materials,
+    );
+    // restando dentro InRun la partita non viene rigenerata, cambia solo lo stato
+    next_state.set(snapshot.state);
+    println!("snapshot loaded from {SNAPSHOT_FILE}");
+}
+
+/// Al primo frame di ogni run sostituisce terreno e creature appena generati con
+/// quelli del livello. Lo stato salvato nel livello viene ignorato: si gioca.
+fn apply_starting_level(
+    mut commands: Commands,
+    mut pending: ResMut<LevelPending>,
+    level: Res<StartingLevel>,
+    in_run: Query<Entity, With<StateScoped<InRun>>>,
+    local_players: Res<LocalPlayers>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !pending.0 {
+        return;
+    }
+    pending.0 = false;
+    restore_snapshot(
+        &mut commands,
+        &level.0,
+        &in_run,
+        &local_players,
+        &mut meshes,
+        &mut materials,
+    );
+}
+
+/// Sostituisce la partita in corso con `snapshot`. Tutto ciò che appartiene a
+/// [`InRun`] viene eliminato e ricreato, quindi le camere si riagganciano ai nuovi player.
+fn restore_snapshot(
+    commands: &mut Commands,
+    snapshot: &WorldSnapshot,
+    in_run: &Query<Entity, With<StateScoped<InRun>>>,
+    local_players: &LocalPlayers,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) {
+    for entity in in_run {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if let Some(level) = snapshot.level {
+        commands.insert_resource(LevelBounds(level));
+    }
+    spawn_terrain_cubes(
+        commands,
+        meshes,
+        materials,
+        snapshot
+            .terrain
+            .iter()
+            .map(|cube| (cube.transform, cube.color)),
+    );
+
+    let creatures: Vec<Entity> = snapshot
+        .creatures
+        .iter()
+        .map(|creature| {
+            let robot = Robot {
+                rope_lenght: creature.rope_lenght,
+            };
+            let Some(slot) = creature.slot else {
+                return commands
+                    .spawn((
+                        robot,
+                        creature.transform,
+                        Visibility::default(),
+                        StateScoped(InRun),
+                    ))
+                    .id();
+            };
+            let binding = local_players
+                .bindings
+                .get(slot)
+                .copied()
+                .unwrap_or_else(|| InputBinding::for_slot(slot));
+            spawn_player_root(
+                commands,
+                slot,
+                binding,
+                creature.transform,
+                robot,
+                TriggerOscillation(creature.oscillation),
+            )
+        })
+        .collect();
+
+    let parts: Vec<Entity> = snapshot
+        .parts
+        .iter()
+        .map(|part| {
+            let rigid_body = match part.mass {
+                // la massa vera viene messa sotto, dopo aver creato il bundle
+                Some(_) => MyRigidBody::Dynamic { mass: 1. },
+                None => MyRigidBody::Fixed,
+            };
+            let shape = match part.shape {
+                PartShape::Ball { radius } => Shape::Ball { radius },
+                PartShape::Rect { width, height } => Shape::Rect {
+                    width,
+                    heigt: height,
+                },
+            };
+            let mut bundle = GenericMechanicalComponentBundle::new(
+                rigid_body,
+                shape,
+                part.color,
+                part.transform,
+                meshes,
+                materials,
+            );
+            bundle.velocity = Velocity {
+                linvel: part.linvel,
+                angvel: part.angvel,
+            };
+            bundle.damping = Damping {
+                linear_damping: part.linear_damping,
+                angular_damping: part.angular_damping,
+            };
+            bundle.gravity_scale = GravityScale(part.gravity_scale);
+            if let Some(mass) = part.mass {
+                bundle.collider_mass = mass.into();
+            }
+
+            let mut entity = commands.spawn(bundle);
+            match part.kind {
+                PartKind::Head => {
+                    entity.insert(RobotHead);
+                }
+                PartKind::Body => {
+                    entity.insert(RobotBody);
+                }
+                PartKind::Debris => {}
+            }
+            match part.creature.and_then(|creature| creatures.get(creature)) {
+                Some(&creature) => {
+                    entity.insert(PartOf(creature)).set_parent(creature);
+                }
+                // senza un player come genitore va eliminato a parte
+                None => {
+                    entity.insert(StateScoped(InRun));
+                }
+            }
+            entity.id()
+        })
+        .collect();
+
+    for (creature, &entity) in snapshot.creatures.iter().zip(&creatures) {
+        match parts.get(creature.head) {
+            Some(&head) => {
+                commands.entity(entity).insert(Head(head));
+            }
+            None => println!("snapshot: creature {entity} has no head"),
+        }
+    }
+
+    for (part, &entity) in snapshot.parts.iter().zip(&parts) {
+        let Some(joint) = &part.joint else {
+            continue;
+        };
+        let Some(&parent) = parts.get(joint.parent) else {
+            println!(
+                "snapshot: joint of {entity} points to missing part {}",
+                joint.parent
+            );
+            continue;
+        };
+        let mut entity = commands.entity(entity);
+        match &joint.kind {
+            JointKind::Rope { max_distance } => {
+                let rope = RopeJointBuilder::new(*max_distance)
+                    .local_anchor1(joint.anchor1)
+                    .local_anchor2(joint.anchor2);
+                entity.insert((ImpulseJoint::new(parent, rope), RopeLength(*max_distance)));
+            }
+            JointKind::Revolute {
+                limits,
+                motor,
+                oscillator,
+            } => {
+                let mut revolute = RevoluteJointBuilder::new()
+                    .local_anchor1(joint.anchor1)
+                    .local_anchor2(joint.anchor2);
+                if let Some(limits) = limits {
+                    revolute = revolute.limits(*limits);
+                }
+                if let Some(motor) = motor {
+                    revolute = revolute
+                        .motor(
+                            motor.target_pos,
+                            motor.target_vel,
+                            motor.stiffness,
+                            motor.damping,
+                        )
+                        .motor_max_force(motor.max_force);
+                }
+                entity.insert(ImpulseJoint::new(parent, revolute));
+                if let Some(oscillator) = oscillator {
+                    entity.insert(*oscillator);
+                }
+            }
+        }
+        if let Some(breakable) = joint.breakable {
+            entity.insert(breakable);
+        }
+    }
+}
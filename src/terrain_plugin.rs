@@ -1,25 +1,48 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use rand::Rng;
 
 use crate::{
     mechanical_components::generic::{
         GenericMechanicalComponentBundle, MyPosition, MyRigidBody, Shape,
     },
+    game_state::{GameRng, InRun},
     player_plugin::Player,
 };
 
-#[derive(Component)]
-struct Cube {
+/// Cambiando `color` (es. dall'inspector) il materiale del cubo viene aggiornato.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Cube {
     color_handle: Handle<ColorMaterial>,
     color: Color,
 }
-#[derive(Component)]
+impl Cube {
+    pub fn color(&self) -> Color {
+        self.color
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 struct Terrain;
+
+/// Rettangolo giocabile del livello: la camera non mostra niente al di fuori.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct LevelBounds(pub Rect);
+
+/// Spazio sopra il terreno che fa ancora parte del livello.
+const LEVEL_HEIGHT: f32 = 8_000.;
+/// Spostamento verticale massimo di ogni cubo rispetto alla fila, scelto da [`GameRng`].
+const TERRAIN_ROUGHNESS: f32 = 30.;
 pub struct TerrainPlugin;
 
 impl Plugin for TerrainPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_terrain);
+        app.register_type::<Cube>()
+            .register_type::<Terrain>()
+            .add_systems(OnEnter(InRun), spawn_terrain)
+            .add_systems(Update, apply_cube_colors);
     }
 }
 
@@ -51,10 +74,13 @@ fn spawn_cube(
     ));
 }
 
+/// Altezze dei cubi e tinta di partenza dei colori vengono da [`GameRng`]: con lo
+/// stesso seed il livello è sempre lo stesso.
 fn spawn_terrain(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rng: ResMut<GameRng>,
 ) {
     let lenght = 2_000.;
     let square_size = 100.;
@@ -64,36 +90,79 @@ fn spawn_terrain(
     let gap = 5.;
     let color = Color::hsl(360. / square_nums as f32, 0.95, 0.6);
 
-    let terrain_cube = commands.spawn((Terrain, Transform::from_xyz(0., 0., 0.), Visibility::default())).id();
-    commands.entity(terrain_cube).with_children(|parent| {
-        (0..square_nums).for_each(|i| {
+    let first_x = -lenght / 2.;
+    let last_x = first_x + (square_size + gap) * (square_nums - 1) as f32;
+    let floor_y = -1000. - half_square_size - TERRAIN_ROUGHNESS;
+    commands.insert_resource(LevelBounds(Rect::new(
+        first_x - half_square_size,
+        floor_y,
+        last_x + half_square_size,
+        floor_y + LEVEL_HEIGHT,
+    )));
+
+    let first_hue = rng.rng.gen_range(0.0..360.);
+    let cubes: Vec<_> = (0..square_nums)
+        .map(|i| {
             let x = -lenght / 2. + (square_size + gap) * (i as f32);
             //println!("{}",x);
-            let y = -1000.;
-            let color = Color::hsl(360. * i as f32 / square_nums as f32, 0.95, 0.6);
-            let color_handle = materials.add(color);
+            let y = -1000. + rng.rng.gen_range(-TERRAIN_ROUGHNESS..=TERRAIN_ROUGHNESS);
+            let hue = (first_hue + 360. * i as f32 / square_nums as f32) % 360.;
+            let color = Color::hsl(hue, 0.95, 0.6);
+            (MyPosition { x, y }.to_transform(), color)
+        })
+        .collect();
+    spawn_terrain_cubes(&mut commands, &mut meshes, &mut materials, cubes);
+}
+
+/// Crea il terreno con un cubo fisso per ogni `(transform, colore)`.
+pub(crate) fn spawn_terrain_cubes(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    cubes: impl IntoIterator<Item = (Transform, Color)>,
+) {
+    let terrain_cube = commands
+        .spawn((
+            Terrain,
+            Transform::from_xyz(0., 0., 0.),
+            Visibility::default(),
+            StateScoped(InRun),
+        ))
+        .id();
+    commands.entity(terrain_cube).with_children(|parent| {
+        for (transform, color) in cubes {
+            let bundle = GenericMechanicalComponentBundle::new(
+                MyRigidBody::Fixed,
+                Shape::Rect {
+                    width: CUBE_LENGTH,
+                    heigt: CUBE_LENGTH,
+                },
+                color,
+                transform,
+                meshes,
+                materials,
+            );
 
             parent.spawn((
+                // lo stesso materiale della mesh, così cambiare `color` si vede
                 Cube {
-                    color_handle: color_handle.clone(),
+                    color_handle: bundle.material.0.clone(),
                     color,
                 },
-                GenericMechanicalComponentBundle::new(
-                    MyRigidBody::Fixed,
-                    Shape::Rect {
-                        width: CUBE_LENGTH,
-                        heigt: CUBE_LENGTH,
-                    },
-                    color,
-                    MyPosition { x, y }.to_transform(),
-                    &mut meshes,
-                    &mut materials,
-                ),
+                bundle,
             ));
-        });
+        }
     });
 }
 
+fn apply_cube_colors(cubes: Query<&Cube, Changed<Cube>>, mut materials: ResMut<Assets<ColorMaterial>>) {
+    for cube in &cubes {
+        if let Some(material) = materials.get_mut(&cube.color_handle) {
+            material.color = cube.color;
+        }
+    }
+}
+
 fn update_material_color(materials: &mut ResMut<Assets<ColorMaterial>>, terrain_cube: &Cube) {
     let material = materials.get_mut(&terrain_cube.color_handle).unwrap();
     let old_color = terrain_cube.color.to_srgba();